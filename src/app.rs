@@ -0,0 +1,2207 @@
+// src/app.rs
+use crate::cli::{Args, FileArg};
+use crate::diff::{self, DiffLine};
+use crate::document::{self, Document};
+use crate::encoding::{self, TextEncoding};
+use crate::fileio::{self, BackupMode};
+use crate::find_in_files::{self, FindInFiles};
+use crate::fonts::{self, Fonts};
+use crate::goto::{self, Target};
+use crate::gutter;
+use crate::highlight::{self, Highlighting, SyntaxTheme};
+use crate::indent::Indent;
+use crate::keymap::{self, Command, Keymap};
+use crate::line_ending::{self, LineEnding};
+use crate::notifications::Notifications;
+use crate::preferences::{Preferences, MAX_ZOOM, MIN_ZOOM};
+use crate::search::{self, PresetTarget, PreviewItem, Query};
+use crate::session::{Session, SESSION_KEY};
+use crate::swap::{self, SwapFile};
+use crate::theme::{self, Theme, ThemeChoice};
+use crate::undo::EditKind;
+use crate::watcher::FileWatcher;
+use eframe::egui;
+use rfd::FileDialog;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Instant;
+
+// How many closed tabs can be brought back with Ctrl+Shift+T
+const MAX_CLOSED_TABS: usize = 20;
+
+pub struct RpadApp {
+    prefs: Preferences,
+    documents: Vec<Document>,
+    active: usize,
+    show_about: bool,
+    find_text: String,
+    replace_text: String,
+    show_find_replace: bool,
+    // Name typed into the Presets menu
+    preset_name: String,
+    show_undo_history: bool,
+    show_goto: bool,
+    // Line where a drag in the line-number gutter started
+    gutter_drag: Option<usize>,
+    goto_text: String,
+    goto_error: Option<String>,
+    // "In selection only": the selection when the option was turned on
+    search_scope: Option<SearchScope>,
+    replace_preview: Option<ReplacePreview>,
+    find_in_files: FindInFiles,
+    // Built-in themes and the user's theme files
+    themes: Vec<Theme>,
+    theme: Theme,
+    syntax_theme: SyntaxTheme,
+    // The desktop theme `theme` was chosen for; None until one has been applied
+    theme_applied_for: Option<Option<eframe::Theme>>,
+    theme_dialog: Option<ThemeDialog>,
+    fonts: Fonts,
+    keymap: Keymap,
+    show_shortcuts: bool,
+    show_font_dialog: bool,
+    // Offer every installed font for the editor, not only monospaced ones
+    show_all_fonts: bool,
+    closed_tabs: Vec<Document>,
+    pending_action: Option<PendingAction>,
+    // Files the user chose not to save while exiting; they stay open so the session keeps them
+    exit_discarded: Vec<u64>,
+    allow_close: bool,
+    last_autosave: Instant,
+    recoveries: Vec<Recovery>,
+    // Files in the recovery store that belong to this window
+    recovery_files: HashSet<String>,
+    notifications: Notifications,
+    save_failure: Option<SaveFailure>,
+    // False for --new-window, so a second window doesn't overwrite the main session
+    persist_session: bool,
+    // Documents that --wait is waiting on; the window closes once they are all closed
+    wait_for: Option<Vec<u64>>,
+    watcher: Option<FileWatcher>,
+    external_changes: Vec<ExternalChange>,
+}
+
+struct SearchScope {
+    document: u64,
+    range: Range<usize>,
+}
+
+// Replace All waiting for the user to pick which replacements to make
+struct ReplacePreview {
+    document: u64,
+    // The text the replacements were computed against
+    content: String,
+    items: Vec<PreviewItem>,
+}
+
+// The theme settings window, open
+struct ThemeDialog {
+    // Restored on Cancel; picking a theme applies it straight away so it can be seen in place
+    original: ThemeChoice,
+    preview: Highlighting,
+}
+
+// A modified document whose file was changed or deleted by another program
+struct ExternalChange {
+    document: u64,
+    deleted: bool,
+    diff: Option<Vec<DiffLine>>,
+}
+
+// A save that failed in a way the user must deal with, e.g. permission denied or disk full
+struct SaveFailure {
+    document: u64,
+    message: String,
+}
+
+// A swap file found on startup, left behind by a session that didn't exit cleanly
+struct Recovery {
+    // The swap file itself, removed once the changes are restored or discarded
+    file: PathBuf,
+    swap: SwapFile,
+    diff: Option<Vec<DiffLine>>,
+}
+
+// Actions that would discard a modified buffer and must be confirmed first
+#[derive(Clone, Copy, PartialEq)]
+enum PendingAction {
+    CloseTab(u64),
+    Exit,
+}
+
+impl Default for RpadApp {
+    fn default() -> Self {
+        Self {
+            prefs: Preferences::default(),
+            documents: vec![Document::default()],
+            active: 0,
+            show_about: false,
+            find_text: String::new(),
+            replace_text: String::new(),
+            show_find_replace: false,
+            preset_name: String::new(),
+            show_undo_history: false,
+            show_goto: false,
+            gutter_drag: None,
+            goto_text: String::new(),
+            goto_error: None,
+            search_scope: None,
+            replace_preview: None,
+            find_in_files: FindInFiles::default(),
+            themes: Theme::builtin(),
+            theme: Theme::dark(),
+            syntax_theme: SyntaxTheme::new(&Theme::dark()),
+            theme_applied_for: None,
+            theme_dialog: None,
+            fonts: Fonts::default(),
+            keymap: Keymap::default(),
+            show_shortcuts: false,
+            show_font_dialog: false,
+            show_all_fonts: false,
+            closed_tabs: Vec::new(),
+            pending_action: None,
+            exit_discarded: Vec::new(),
+            allow_close: false,
+            last_autosave: Instant::now(),
+            recoveries: Vec::new(),
+            recovery_files: HashSet::new(),
+            notifications: Notifications::default(),
+            save_failure: None,
+            persist_session: true,
+            wait_for: None,
+            watcher: None,
+            external_changes: Vec::new(),
+        }
+    }
+}
+
+impl RpadApp {
+    pub fn new(cc: &eframe::CreationContext<'_>, args: Args, stdin: Option<Vec<u8>>) -> Self {
+        let mut app = Self {
+            persist_session: !args.new_window,
+            ..Default::default()
+        };
+        match FileWatcher::new(cc.egui_ctx.clone()) {
+            Ok(watcher) => app.watcher = Some(watcher),
+            Err(e) => app
+                .notifications
+                .warn(format!("Changes made to files by other programs won't be detected: {}", e)),
+        }
+        // The zoom keys belong to the keymap and zoom the editor; egui's own would scale the
+        // interface behind the Interface Scale setting's back
+        cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        app.fonts.start_loading(&cc.egui_ctx);
+        app.load_keymap();
+        let (themes, errors) = theme::load_all();
+        app.themes = themes;
+        for error in errors {
+            app.notifications.warn(error);
+        }
+        if let Some(storage) = cc.storage {
+            if let Some(prefs_str) = storage.get_string(eframe::APP_KEY)
+                && let Ok(prefs) = serde_json::from_str::<Preferences>(&prefs_str)
+            {
+                app.prefs = prefs;
+            }
+            if app.persist_session
+                && let Some(session_str) = storage.get_string(SESSION_KEY)
+                && let Ok(session) = serde_json::from_str::<Session>(&session_str)
+            {
+                let (documents, active) = session.restore();
+                app.recovery_files = session.tabs.iter().filter_map(|t| t.recovery.clone()).collect();
+                if !documents.is_empty() {
+                    app.documents = documents;
+                    app.active = active;
+                }
+            }
+        }
+        app.recoveries = swap::find_orphans()
+            .into_iter()
+            .map(|(file, swap)| Recovery { file, swap, diff: None })
+            .collect();
+
+        let mut opened = Vec::new();
+        for file in args.files {
+            if let Some(index) = app.open_from_command_line(file) {
+                opened.push(index);
+            }
+        }
+        if let Some(bytes) = stdin {
+            opened.push(app.add_document(Document::from_bytes(None, &bytes)));
+        }
+        for &index in &opened {
+            app.documents[index].read_only |= args.readonly;
+        }
+        if let Some(&last) = opened.last() {
+            app.active = last;
+        }
+        if args.wait {
+            // Nothing to wait on, and the window would close before the errors could be read
+            if opened.is_empty() {
+                for message in app.notifications.errors() {
+                    eprintln!("rpad: {}", message);
+                }
+                eprintln!("rpad: --wait: no files could be opened");
+                std::process::exit(1);
+            }
+            app.wait_for = Some(opened.iter().map(|&index| app.documents[index].id).collect());
+        }
+        app
+    }
+
+    fn open_from_command_line(&mut self, file: FileArg) -> Option<usize> {
+        let path = std::path::absolute(&file.path).unwrap_or(file.path);
+        // Like Notepad, a file that doesn't exist yet is created on first save
+        let index = if path.exists() {
+            self.open_path(path)?
+        } else {
+            let mut doc = Document::default();
+            doc.path = Some(path);
+            self.add_document(doc)
+        };
+        if let Some(line) = file.line {
+            let doc = &mut self.documents[index];
+            let cursor = doc.char_index(line, file.column.unwrap_or(1));
+            doc.set_cursor(cursor);
+        }
+        Some(index)
+    }
+
+    // Replaces an untouched "Untitled" tab, otherwise opens a new one
+    fn add_document(&mut self, doc: Document) -> usize {
+        if self.doc().is_blank() {
+            self.documents[self.active] = doc;
+        } else {
+            self.documents.push(doc);
+            self.active = self.documents.len() - 1;
+        }
+        self.active
+    }
+
+    fn doc(&self) -> &Document {
+        &self.documents[self.active]
+    }
+
+    fn doc_mut(&mut self) -> &mut Document {
+        &mut self.documents[self.active]
+    }
+
+    fn index_of(&self, id: u64) -> Option<usize> {
+        self.documents.iter().position(|d| d.id == id)
+    }
+
+    fn new_file(&mut self) {
+        self.documents.push(Document::default());
+        self.active = self.documents.len() - 1;
+    }
+
+    fn open_file(&mut self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("Text Files", &["txt"])
+            .add_filter("All Files", &["*"])
+            .pick_file()
+        {
+            self.open_path(path);
+        }
+    }
+
+    fn open_path(&mut self, path: PathBuf) -> Option<usize> {
+        // Switch to the tab if the file is already open
+        if let Some(index) = self.documents.iter().position(|d| d.path.as_ref() == Some(&path)) {
+            self.active = index;
+            return Some(index);
+        }
+
+        match Document::open(path.clone()) {
+            Ok(doc) => {
+                if doc.mixed_line_endings {
+                    self.notifications.warn(format!(
+                        "{} has mixed line endings; they will be converted to {} when saved",
+                        doc.display_name(),
+                        doc.line_ending.label()
+                    ));
+                }
+                if doc.malformed {
+                    self.notifications.warn(malformed_warning(&doc));
+                }
+                Some(self.add_document(doc))
+            }
+            Err(e) => {
+                self.notifications.error(format!("Failed to open {}: {}", path.display(), e));
+                None
+            }
+        }
+    }
+
+    fn reopen_with_encoding(&mut self, encoding: &'static encoding_rs::Encoding) {
+        let doc = &mut self.documents[self.active];
+        let name = doc.display_name().to_owned();
+        match doc.reload_with(encoding) {
+            Ok(()) if doc.malformed => self.notifications.warn(malformed_warning(doc)),
+            Ok(()) => self.notifications.info(format!("Reopened {} as {}", name, doc.encoding.label())),
+            Err(e) => self.notifications.error(format!("Failed to open {}: {}", name, e)),
+        }
+    }
+
+    fn save_with_encoding(&mut self, encoding: TextEncoding) {
+        let previous = self.doc().encoding;
+        self.doc_mut().encoding = encoding;
+        if !self.save_file(self.active) {
+            self.doc_mut().encoding = previous;
+        }
+    }
+
+    // Returns true if the document was written to disk
+    fn save_file(&mut self, index: usize) -> bool {
+        if let Some(path) = self.documents[index].path.clone() {
+            self.save_to_path(index, path)
+        } else {
+            self.save_as_file(index)
+        }
+    }
+
+    fn save_as_file(&mut self, index: usize) -> bool {
+        if let Some(path) = FileDialog::new()
+            .add_filter("Text Files", &["txt"])
+            .set_file_name(self.documents[index].display_name())
+            .save_file()
+        {
+            self.save_to_path(index, path)
+        } else {
+            false
+        }
+    }
+
+    fn save_to_path(&mut self, index: usize, path: PathBuf) -> bool {
+        let doc = &mut self.documents[index];
+        let result = doc
+            .encode()
+            .and_then(|bytes| fileio::write_atomic(&path, &bytes, self.prefs.backup));
+        match result {
+            Ok(_) => {
+                doc.disk_stamp = document::disk_stamp(&path);
+                if let Some(old_path) = doc.path.replace(path) {
+                    swap::remove(&old_path);
+                }
+                doc.mark_saved();
+                true
+            }
+            Err(e) => {
+                let message = format!("Failed to save {}: {}", path.display(), e);
+                if is_critical(&e) {
+                    self.save_failure = Some(SaveFailure {
+                        document: doc.id,
+                        message: message.clone(),
+                    });
+                }
+                self.notifications.error(message);
+                false
+            }
+        }
+    }
+
+    fn request_close_tab(&mut self, index: usize) {
+        if self.documents[index].is_modified {
+            self.active = index;
+            self.pending_action = Some(PendingAction::CloseTab(self.documents[index].id));
+        } else {
+            self.close_tab(index);
+        }
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        let doc = self.documents.remove(index);
+        if let Some(path) = &doc.path {
+            swap::remove(path);
+        }
+        if let Some(ids) = &mut self.wait_for {
+            ids.retain(|&id| id != doc.id);
+        }
+        if !doc.is_blank() {
+            self.closed_tabs.push(doc);
+            if self.closed_tabs.len() > MAX_CLOSED_TABS {
+                self.closed_tabs.remove(0);
+            }
+        }
+        if self.documents.is_empty() {
+            self.documents.push(Document::default());
+        }
+        if self.active > index || self.active >= self.documents.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+    }
+
+    fn reopen_closed_tab(&mut self) {
+        if let Some(doc) = self.closed_tabs.pop() {
+            self.add_document(doc);
+        }
+    }
+
+    fn cycle_tab(&mut self, forward: bool) {
+        let count = self.documents.len();
+        self.active = if forward {
+            (self.active + 1) % count
+        } else {
+            (self.active + count - 1) % count
+        };
+    }
+
+    // Asks about each modified document in turn, then closes the window
+    fn request_exit(&mut self, ctx: &egui::Context) {
+        if let Some(index) = self.next_unsaved_on_exit() {
+            self.active = index;
+            self.pending_action = Some(PendingAction::Exit);
+        } else {
+            self.allow_close = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    // The document the unsaved changes dialog is asking about
+    fn pending_target(&self, action: PendingAction) -> Option<usize> {
+        match action {
+            PendingAction::CloseTab(id) => self.index_of(id),
+            PendingAction::Exit => self.next_unsaved_on_exit(),
+        }
+    }
+
+    fn next_unsaved_on_exit(&self) -> Option<usize> {
+        self.documents
+            .iter()
+            .position(|d| d.is_modified && !self.exit_discarded.contains(&d.id))
+    }
+
+    fn resolve_pending(&mut self, ctx: &egui::Context, action: PendingAction, save: bool) {
+        self.pending_action = None;
+        let Some(index) = self.pending_target(action) else {
+            return;
+        };
+        if save && !self.save_file(index) {
+            return;
+        }
+        match action {
+            PendingAction::CloseTab(_) => self.close_tab(index),
+            PendingAction::Exit => {
+                // A declined file stays in the session and reopens as it is on disk. An untitled
+                // buffer has nothing on disk, so it is closed, or the recovery store would keep it.
+                if !save && self.documents[index].path.is_none() {
+                    self.close_tab(index);
+                } else if !save {
+                    self.exit_discarded.push(self.documents[index].id);
+                }
+                self.request_exit(ctx);
+            }
+        }
+    }
+
+    // Copies every modified file-backed buffer into the swap directory
+    fn autosave(&mut self) {
+        for doc in &self.documents {
+            if doc.is_modified
+                && let Some(path) = &doc.path
+                && let Err(e) = swap::write(path, &doc.content)
+            {
+                self.notifications.warn(format!("Autosave of {} failed: {}", doc.display_name(), e));
+            }
+        }
+        self.last_autosave = Instant::now();
+    }
+
+    fn restore_recovery(&mut self, swap: SwapFile) {
+        let index = match self.documents.iter().position(|d| d.path.as_ref() == Some(&swap.path)) {
+            Some(index) => index,
+            None => {
+                // Open the file for its encoding and line ending; it may no longer exist
+                let doc = Document::open(swap.path.clone()).unwrap_or_default();
+                self.documents.push(doc);
+                self.documents.len() - 1
+            }
+        };
+        self.notifications.info(format!("Restored unsaved changes to {}", swap.path.display()));
+        let doc = &mut self.documents[index];
+        doc.path = Some(swap.path);
+        doc.content = swap.content;
+        doc.commit_edit(EditKind::Command("Restore Unsaved Changes"));
+        doc.mark_unsaved();
+        self.active = index;
+        // Take ownership of the swap file right away in case we crash again
+        self.autosave();
+    }
+
+    fn show_recovery_dialog(&mut self, ctx: &egui::Context) {
+        let mut restore = None;
+        let mut discard = None;
+        let mut open = true;
+        egui::Window::new("Recover Unsaved Changes")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("rpad did not exit cleanly. The following unsaved changes were found:");
+                ui.separator();
+                let documents = &self.documents;
+                for (index, recovery) in self.recoveries.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(recovery.swap.path.display().to_string());
+                        ui.weak(recovery.swap.age_text());
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Restore").clicked() {
+                            restore = Some(index);
+                        }
+                        let diff_label = if recovery.diff.is_some() { "Hide Diff" } else { "Diff" };
+                        if ui.button(diff_label).clicked() {
+                            recovery.diff = match recovery.diff {
+                                Some(_) => None,
+                                None => {
+                                    // Decoded like the tab would be: its encoding if it's open, else detected
+                                    let path = recovery.swap.path.clone();
+                                    let opened = match documents.iter().find(|d| d.path.as_ref() == Some(&path)) {
+                                        Some(doc) => Document::open_with(path, doc.encoding.encoding),
+                                        None => Document::open(path),
+                                    };
+                                    let on_disk = opened.map(|d| d.content).unwrap_or_default();
+                                    Some(diff::line_diff(&on_disk, &recovery.swap.content))
+                                }
+                            };
+                        }
+                        if ui.button("Discard").clicked() {
+                            discard = Some(index);
+                        }
+                    });
+                    if let Some(lines) = &recovery.diff {
+                        diff::show_diff(ui, lines);
+                    }
+                    ui.separator();
+                }
+            });
+
+        if let Some(index) = restore {
+            let recovery = self.recoveries.remove(index);
+            let _ = fs::remove_file(&recovery.file);
+            self.restore_recovery(recovery.swap);
+        } else if let Some(index) = discard {
+            let recovery = self.recoveries.remove(index);
+            let _ = fs::remove_file(&recovery.file);
+        }
+        // Closing the dialog leaves the remaining swap files for next time
+        if !open {
+            self.recoveries.clear();
+        }
+    }
+
+    fn show_save_failure_dialog(&mut self, ctx: &egui::Context) {
+        let Some(failure) = &self.save_failure else {
+            return;
+        };
+        let Some(index) = self.index_of(failure.document) else {
+            self.save_failure = None;
+            return;
+        };
+        let message = failure.message.clone();
+        egui::Window::new("Save Failed")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.colored_label(ui.visuals().error_fg_color, message);
+                ui.label("Your changes have not been saved.");
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Retry").clicked() {
+                        self.save_failure = None;
+                        self.save_file(index);
+                    }
+                    if ui.button("Save As...").clicked() {
+                        self.save_failure = None;
+                        self.save_as_file(index);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.save_failure = None;
+                    }
+                });
+            });
+    }
+
+    // Unmodified buffers follow their file silently; modified ones ask the user
+    fn check_external_changes(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        watcher.sync(self.documents.iter().filter_map(|d| d.path.as_deref()));
+        for path in watcher.changed_files() {
+            let Some(doc) = self.documents.iter_mut().find(|d| d.path.as_ref() == Some(&path)) else {
+                continue;
+            };
+            // Our own saves, or a touch that didn't change anything we know about
+            if !doc.changed_on_disk() || self.external_changes.iter().any(|c| c.document == doc.id) {
+                continue;
+            }
+            let deleted = !path.exists();
+            if !deleted && !doc.is_modified {
+                if let Err(e) = doc.reload() {
+                    self.notifications.error(format!("Failed to reload {}: {}", path.display(), e));
+                }
+            } else {
+                self.external_changes.push(ExternalChange {
+                    document: doc.id,
+                    deleted,
+                    diff: None,
+                });
+            }
+        }
+    }
+
+    fn show_external_change_dialog(&mut self, ctx: &egui::Context) {
+        let mut resolved = None;
+        let mut reload = None;
+        let mut close = None;
+        egui::Window::new("File Changed on Disk")
+            .collapsible(false)
+            .show(ctx, |ui| {
+                for (index, change) in self.external_changes.iter_mut().enumerate() {
+                    let Some(doc) = self.documents.iter_mut().find(|d| d.id == change.document) else {
+                        resolved = Some(index);
+                        continue;
+                    };
+                    let name = doc.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+                    if change.deleted {
+                        ui.label(format!("{} has been deleted by another program.", name));
+                    } else {
+                        ui.label(format!("{} has been changed by another program.", name));
+                    }
+                    ui.horizontal(|ui| {
+                        if !change.deleted && ui.button("Reload").clicked() {
+                            reload = Some(index);
+                        }
+                        if ui.button("Keep Mine").clicked() {
+                            // Stop asking until the file changes again; the buffer now differs from disk
+                            doc.disk_stamp = doc.path.as_deref().and_then(document::disk_stamp);
+                            doc.mark_unsaved();
+                            resolved = Some(index);
+                        }
+                        if !change.deleted {
+                            let diff_label = if change.diff.is_some() { "Hide Diff" } else { "Diff" };
+                            if ui.button(diff_label).clicked() {
+                                change.diff = match change.diff {
+                                    Some(_) => None,
+                                    None => {
+                                        let on_disk = doc
+                                            .path
+                                            .clone()
+                                            .and_then(|p| Document::open_with(p, doc.encoding.encoding).ok())
+                                            .map(|d| d.content)
+                                            .unwrap_or_default();
+                                        Some(diff::line_diff(&on_disk, &doc.content))
+                                    }
+                                };
+                            }
+                        } else if ui.button("Close Tab").clicked() {
+                            close = Some(index);
+                        }
+                    });
+                    if let Some(lines) = &change.diff {
+                        diff::show_diff(ui, lines);
+                    }
+                    ui.separator();
+                }
+            });
+
+        if let Some(index) = reload {
+            let change = self.external_changes.remove(index);
+            if let Some(doc) = self.documents.iter_mut().find(|d| d.id == change.document)
+                && let Err(e) = doc.reload()
+            {
+                self.notifications.error(format!("Failed to reload {}: {}", doc.display_name(), e));
+            }
+        } else if let Some(index) = close {
+            let change = self.external_changes.remove(index);
+            if let Some(index) = self.index_of(change.document) {
+                self.request_close_tab(index);
+            }
+        } else if let Some(index) = resolved {
+            self.external_changes.remove(index);
+        }
+    }
+
+    // Every state of the active document, including branches that were undone and then typed over
+    fn show_undo_history(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_undo_history;
+        let mut jump = None;
+        let doc = &self.documents[self.active];
+        egui::Window::new("Undo History")
+            .open(&mut open)
+            .default_size([300.0, 400.0])
+            .show(ctx, |ui| {
+                ui.label(doc.display_name());
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for entry in doc.history.entries() {
+                        ui.horizontal(|ui| {
+                            ui.add_space(entry.depth as f32 * 16.0);
+                            if entry.depth > 0 {
+                                ui.weak("└");
+                            }
+                            if ui.selectable_label(entry.is_current, &entry.label).clicked() {
+                                jump = Some(entry.node);
+                            }
+                            ui.weak(format_age(entry.age));
+                        });
+                    }
+                });
+            });
+        self.show_undo_history = open;
+        if let Some(node) = jump
+            && !self.doc().read_only
+        {
+            self.doc_mut().jump_to_history(node);
+        }
+    }
+
+    fn open_goto(&mut self) {
+        self.goto_text.clear();
+        self.goto_error = None;
+        self.show_goto = true;
+    }
+
+    // Moves the cursor to the position typed into the Go To dialog
+    fn go_to(&mut self) -> Result<(), String> {
+        let target = goto::parse(&self.goto_text)?;
+        let doc = self.doc_mut();
+        let (lines, _) = doc.totals();
+        let index = match target {
+            Target::Line { line, column } => {
+                if line > lines {
+                    return Err(format!("There are only {} lines", lines));
+                }
+                doc.char_index(line, column.unwrap_or(1))
+            }
+            Target::Relative(delta) => {
+                let (current, _) = doc.line_column(doc.cursor);
+                let line = (current as isize).saturating_add(delta).clamp(1, lines as isize);
+                doc.char_index(line as usize, 1)
+            }
+            Target::Offset(offset) => doc.char_index_at_file_offset(offset),
+        };
+        doc.set_cursor(index);
+        Ok(())
+    }
+
+    fn show_goto_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut go = false;
+        egui::Window::new("Go To")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let doc = &mut self.documents[self.active];
+                let (line, _) = doc.line_column(doc.cursor);
+                ui.label(format!("Line {} of {}", line, doc.totals().0));
+                let response = ui.add(egui::TextEdit::singleline(&mut self.goto_text).hint_text(goto::HINT));
+                // Just opened: type straight into the field
+                if self.goto_text.is_empty() && self.goto_error.is_none() {
+                    response.request_focus();
+                }
+                if response.changed() {
+                    self.goto_error = None;
+                }
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    go = true;
+                }
+                if let Some(error) = &self.goto_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Go To").clicked() {
+                        go = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.show_goto = false;
+                    }
+                });
+            });
+        if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.show_goto = false;
+        }
+        if go {
+            match self.go_to() {
+                Ok(()) => self.show_goto = false,
+                Err(message) => self.goto_error = Some(message),
+            }
+        }
+    }
+
+    // Switches egui and the highlighter over when the chosen theme, its file or the desktop theme changes
+    fn apply_theme(&mut self, ctx: &egui::Context, system: Option<eframe::Theme>) {
+        let name = match &self.prefs.theme {
+            ThemeChoice::Named(name) => name.as_str(),
+            ThemeChoice::FollowSystem if system == Some(eframe::Theme::Light) => "Light",
+            ThemeChoice::FollowSystem => "Dark",
+        };
+        let theme = self
+            .themes
+            .iter()
+            .find(|t| t.name == name)
+            .cloned()
+            .unwrap_or_else(Theme::dark);
+        // eframe puts back its own visuals when the desktop theme changes, so redo ours then too
+        if theme != self.theme || self.theme_applied_for != Some(system) {
+            ctx.set_visuals(theme.visuals());
+            self.syntax_theme = SyntaxTheme::new(&theme);
+            self.theme = theme;
+            self.theme_applied_for = Some(system);
+        }
+    }
+
+    fn open_theme_dialog(&mut self) {
+        self.theme_dialog = Some(ThemeDialog {
+            original: self.prefs.theme.clone(),
+            preview: Highlighting::default(),
+        });
+    }
+
+    fn reload_themes(&mut self) {
+        let (themes, errors) = theme::load_all();
+        self.themes = themes;
+        for error in errors {
+            self.notifications.warn(error);
+        }
+    }
+
+    // Writes the current theme to the themes folder as a starting point for a custom one
+    fn export_theme(&mut self) {
+        let Some(dir) = theme::themes_dir() else {
+            self.notifications.error("No folder to keep themes in");
+            return;
+        };
+        let mut custom = self.theme.clone();
+        custom.name = format!("{} Custom", self.theme.name);
+        let path = dir.join(format!("{}.toml", custom.name.to_lowercase().replace(' ', "-")));
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(&path, custom.to_toml())) {
+            Ok(()) => {
+                self.notifications
+                    .info(format!("Saved {}; edit it and press Reload to see the changes", path.display()));
+                self.reload_themes();
+                self.prefs.theme = ThemeChoice::Named(custom.name);
+            }
+            Err(e) => self.notifications.error(format!("Failed to save {}: {}", path.display(), e)),
+        }
+    }
+
+    fn show_theme_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut cancel = false;
+        let mut close = false;
+        let mut reload = false;
+        let mut export = false;
+        egui::Window::new("Theme")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.selectable_value(&mut self.prefs.theme, ThemeChoice::FollowSystem, "Follow System");
+                        ui.separator();
+                        for theme in &self.themes {
+                            ui.selectable_value(&mut self.prefs.theme, ThemeChoice::Named(theme.name.clone()), &theme.name);
+                        }
+                    });
+                    ui.separator();
+                    if let Some(dialog) = &mut self.theme_dialog {
+                        theme_preview(ui, &self.theme, &self.syntax_theme, &mut dialog.preview);
+                    }
+                });
+                ui.separator();
+                if let Some(dir) = theme::themes_dir() {
+                    ui.label(format!("Theme files (.toml or .json) are read from {}", dir.display()));
+                }
+                ui.horizontal(|ui| {
+                    reload = ui.button("Reload").clicked();
+                    export = ui.button("Export Current Theme").clicked();
+                    ui.separator();
+                    close = ui.button("OK").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if reload {
+            self.reload_themes();
+        }
+        if export {
+            self.export_theme();
+        }
+        if !open || cancel || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            if let Some(dialog) = self.theme_dialog.take() {
+                self.prefs.theme = dialog.original;
+            }
+        } else if close {
+            self.theme_dialog = None;
+        }
+    }
+
+    fn show_font_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        egui::Window::new("Font")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let Some(system) = self.fonts.system() else {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Looking for installed fonts...");
+                    });
+                    return;
+                };
+                egui::Grid::new("fonts").num_columns(2).show(ui, |ui| {
+                    ui.label("Editor:");
+                    let editor_fonts = if self.show_all_fonts { &system.all } else { &system.monospace };
+                    font_combo(ui, "editor_font", &mut self.prefs.editor_font, fonts::BUNDLED, editor_fonts);
+                    ui.end_row();
+                    ui.label("");
+                    ui.checkbox(&mut self.show_all_fonts, "Show proportional fonts");
+                    ui.end_row();
+                    ui.label("Interface:");
+                    font_combo(ui, "interface_font", &mut self.prefs.interface_font, "Default", &system.all);
+                    ui.end_row();
+                    ui.label("Size:");
+                    ui.add(egui::Slider::new(&mut self.prefs.font_size, 8.0..=32.0));
+                    ui.end_row();
+                });
+                ui.separator();
+                ui.label(egui::RichText::new(FONT_SAMPLE).monospace().size(self.prefs.font_size));
+                ui.label(FONT_SAMPLE);
+                ui.separator();
+                if ui.button("Close").clicked() {
+                    self.show_font_dialog = false;
+                }
+            });
+        if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.show_font_dialog = false;
+        }
+    }
+
+    fn encoding_menus(&mut self, ui: &mut egui::Ui) {
+        let can_reopen = self.doc().path.is_some() && !self.doc().is_modified;
+        ui.add_enabled_ui(can_reopen, |ui| {
+            ui.menu_button("Reopen with Encoding", |ui| {
+                for &encoding in encoding::REOPEN_ENCODINGS {
+                    if ui.button(encoding.name()).clicked() {
+                        self.reopen_with_encoding(encoding);
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+        ui.menu_button("Save with Encoding", |ui| {
+            for &encoding in encoding::SAVE_ENCODINGS {
+                let selected = self.doc().encoding == encoding;
+                if ui.selectable_label(selected, encoding.label()).clicked() {
+                    self.save_with_encoding(encoding);
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+
+    fn line_ending_menu(&mut self, ui: &mut egui::Ui) {
+        for line_ending in LineEnding::ALL {
+            let selected = self.doc().line_ending == line_ending;
+            if ui.selectable_label(selected, line_ending.label()).clicked() {
+                self.doc_mut().set_line_ending(line_ending);
+                ui.close_menu();
+            }
+        }
+    }
+
+    fn indent_menu(&mut self, ui: &mut egui::Ui) {
+        for indent in Indent::ALL {
+            let selected = self.doc().indent == indent;
+            if ui.selectable_label(selected, indent.label()).clicked() {
+                self.doc_mut().indent = indent;
+                ui.close_menu();
+            }
+        }
+    }
+
+    fn run(&mut self, ctx: &egui::Context, command: Command) {
+        match command {
+            Command::NewFile => self.new_file(),
+            Command::OpenFile => self.open_file(),
+            Command::Save => {
+                self.save_file(self.active);
+            }
+            Command::SaveAs => {
+                self.save_as_file(self.active);
+            }
+            Command::CloseTab => self.request_close_tab(self.active),
+            Command::ReopenClosedTab => self.reopen_closed_tab(),
+            Command::NextTab => self.cycle_tab(true),
+            Command::PreviousTab => self.cycle_tab(false),
+            Command::Exit => self.request_exit(ctx),
+            Command::Undo if !self.doc().read_only => self.doc_mut().undo(),
+            Command::Redo if !self.doc().read_only => self.doc_mut().redo(),
+            Command::Undo | Command::Redo => {}
+            Command::UndoHistory => self.show_undo_history = true,
+            Command::FindReplace => self.show_find_replace = true,
+            Command::FindInFiles => self.show_find_in_files(),
+            Command::FindNext => self.find_next(true),
+            Command::FindPrevious => self.find_next(false),
+            Command::GoTo => self.open_goto(),
+            Command::WordWrap => self.prefs.word_wrap ^= true,
+            Command::Font => self.show_font_dialog = true,
+            Command::StatusBar => self.prefs.status_bar ^= true,
+            Command::LineNumbers => self.prefs.line_numbers ^= true,
+            Command::ZoomIn => self.step_zoom(true),
+            Command::ZoomOut => self.step_zoom(false),
+            Command::ZoomReset => self.set_zoom(1.0),
+            Command::Theme => self.open_theme_dialog(),
+            Command::KeyboardShortcuts => self.show_shortcuts = true,
+        }
+    }
+
+    // A menu entry for a command, labelled with its shortcut
+    fn menu_item(&mut self, ui: &mut egui::Ui, command: Command, enabled: bool) {
+        if ui.add_enabled(enabled, egui::Button::new(self.keymap.label(command))).clicked() {
+            self.run(&ui.ctx().clone(), command);
+            ui.close_menu();
+        }
+    }
+
+    fn load_keymap(&mut self) {
+        self.keymap = Keymap::load();
+        for problem in &self.keymap.problems {
+            self.notifications.warn(problem.clone());
+        }
+    }
+
+    // Writes the built-in bindings out as a keymap file to edit, unless there already is one
+    fn create_keymap_file(&mut self) {
+        let Some(path) = keymap::keymap_path() else {
+            self.notifications.error("No folder to keep the keymap in");
+            return;
+        };
+        if path.exists() {
+            self.notifications.info(format!("Edit {} and press Reload", path.display()));
+            return;
+        }
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, Keymap::template()));
+        match written {
+            Ok(()) => self.notifications.info(format!("Created {}; edit it and press Reload", path.display())),
+            Err(e) => self.notifications.error(format!("Failed to create {}: {}", path.display(), e)),
+        }
+    }
+
+    fn show_shortcuts_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_shortcuts;
+        let mut reload = false;
+        let mut create = false;
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                for problem in &self.keymap.problems {
+                    ui.colored_label(ui.visuals().warn_fg_color, problem);
+                }
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    egui::Grid::new("shortcuts").num_columns(3).striped(true).show(ui, |ui| {
+                        for command in Command::ALL {
+                            ui.label(command.title().trim_end_matches("..."));
+                            ui.label(egui::RichText::new(command.id()).monospace().weak());
+                            ui.label(self.keymap.bindings(command).join(", "));
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.separator();
+                if let Some(path) = keymap::keymap_path() {
+                    ui.label(format!("Bindings are read from {}", path.display()));
+                }
+                ui.horizontal(|ui| {
+                    create = ui.button("Edit Keymap File").clicked();
+                    reload = ui.button("Reload").clicked();
+                });
+            });
+        self.show_shortcuts = open;
+        if create {
+            self.create_keymap_file();
+        }
+        if reload {
+            self.load_keymap();
+        }
+    }
+
+    // The editor's zoom, as a factor of the font size
+    fn zoom(&self) -> f32 {
+        match self.doc().zoom {
+            Some(zoom) if self.prefs.per_document_zoom => zoom,
+            _ => self.prefs.zoom,
+        }
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        // Whole percents, so repeated wheel steps don't drift
+        let zoom = (zoom.clamp(MIN_ZOOM, MAX_ZOOM) * 100.0).round() / 100.0;
+        if self.prefs.per_document_zoom {
+            self.doc_mut().zoom = Some(zoom);
+        } else {
+            self.prefs.zoom = zoom;
+        }
+    }
+
+    // To the next 10% up or down, like Notepad
+    fn step_zoom(&mut self, zoom_in: bool) {
+        let tenths = self.zoom() * 10.0;
+        let tenths = if zoom_in { tenths.floor() + 1.0 } else { tenths.ceil() - 1.0 };
+        self.set_zoom(tenths / 10.0);
+    }
+
+    fn zoom_menu(&mut self, ui: &mut egui::Ui) {
+        // Zooming in and out leaves the menu open to zoom again
+        if ui.button(self.keymap.label(Command::ZoomIn)).clicked() {
+            self.step_zoom(true);
+        }
+        if ui.button(self.keymap.label(Command::ZoomOut)).clicked() {
+            self.step_zoom(false);
+        }
+        self.menu_item(ui, Command::ZoomReset, true);
+        ui.separator();
+        for percent in [50, 75, 100, 125, 150, 200, 300] {
+            let zoom = percent as f32 / 100.0;
+            if ui.selectable_label(self.zoom() == zoom, format!("{}%", percent)).clicked() {
+                self.set_zoom(zoom);
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        // Tabs that haven't been zoomed on their own follow the shared zoom
+        ui.checkbox(&mut self.prefs.per_document_zoom, "Zoom Each Tab Separately");
+    }
+
+    fn language_menu(&mut self, ui: &mut egui::Ui) {
+        let doc = self.doc_mut();
+        if ui.selectable_label(doc.highlighting.language.is_none(), "Auto Detect").clicked() {
+            doc.highlighting.language = None;
+            ui.close_menu();
+        }
+        ui.separator();
+        egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            for language in highlight::languages() {
+                let selected = doc.highlighting.language.as_deref() == Some(language);
+                if ui.selectable_label(selected, language).clicked() {
+                    doc.highlighting.language = Some(language.to_owned());
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+
+    fn get_title(&self) -> String {
+        format!("{} - rpad", self.doc().tab_label())
+    }
+
+    // The Find pattern compiled with the current options
+    fn query(&self) -> Result<Option<Query>, String> {
+        Query::new(&self.find_text, self.prefs.search)
+    }
+
+    // The byte range searches are limited to in the active document, if any
+    fn scope(&self) -> Option<Range<usize>> {
+        self.search_scope
+            .as_ref()
+            .filter(|scope| scope.document == self.doc().id)
+            .map(|scope| scope.range.clone())
+    }
+
+    fn set_selection_scope(&mut self, enabled: bool) {
+        if !enabled {
+            self.search_scope = None;
+            return;
+        }
+        let range = self.doc().selection_bytes();
+        if range.is_empty() {
+            self.notifications.info("Select the text to search in first");
+            return;
+        }
+        self.search_scope = Some(SearchScope {
+            document: self.doc().id,
+            range,
+        });
+    }
+
+    // Keeps the scope covering the same text after a replacement inside it
+    fn resize_scope(&mut self, removed: usize, inserted: usize) {
+        if let Some(scope) = &mut self.search_scope
+            && scope.document == self.documents[self.active].id
+        {
+            scope.range.end = scope.range.end + inserted - removed;
+        }
+    }
+
+    // Selects the next (or previous) match after the selection, wrapping around the ends
+    fn find_next(&mut self, forward: bool) {
+        let Ok(Some(query)) = self.query() else {
+            // Nothing to search for, or a bad pattern: the Find window shows why
+            self.show_find_replace = true;
+            return;
+        };
+        self.prefs.search_history.remember_find(&self.find_text);
+        let doc = self.doc();
+        let matches = query.find_all(&doc.content, self.scope());
+        let Some((index, wrapped)) = search::next_match(&matches, doc.selection_bytes(), forward) else {
+            self.notifications.info(format!("Cannot find \"{}\"", self.find_text));
+            return;
+        };
+        if wrapped {
+            let area = if self.scope().is_some() { "selection" } else { "document" };
+            self.notifications.info(if forward {
+                format!("Reached the end of the {}, continued from the top", area)
+            } else {
+                format!("Reached the start of the {}, continued from the bottom", area)
+            });
+        }
+        self.doc_mut().select_bytes(matches[index].clone());
+    }
+
+    // "3 of 17" when a match is selected, otherwise just the total
+    fn match_count(&self, query: &Query) -> String {
+        let doc = self.doc();
+        let matches = query.find_all(&doc.content, self.scope());
+        let selection = doc.selection_bytes();
+        match matches.iter().position(|m| *m == selection) {
+            _ if matches.is_empty() => "No matches".to_owned(),
+            Some(index) => format!("{} of {}", index + 1, matches.len()),
+            None if matches.len() == 1 => "1 match".to_owned(),
+            None => format!("{} matches", matches.len()),
+        }
+    }
+
+    // Replaces the selected match, if it is one, and moves on to the next
+    fn replace_next(&mut self) {
+        if self.doc().read_only {
+            self.notifications.warn(format!("{} is read-only", self.doc().display_name()));
+            return;
+        }
+        let Ok(Some(query)) = self.query() else {
+            self.show_find_replace = true;
+            return;
+        };
+        self.prefs.search_history.remember_find(&self.find_text);
+        self.prefs.search_history.remember_replace(&self.replace_text);
+        let selection = self.doc().selection_bytes();
+        let Some(replacement) = query
+            .replacements(&self.doc().content, &self.replace_text, self.scope())
+            .into_iter()
+            .find(|r| r.range == selection)
+        else {
+            self.find_next(true);
+            return;
+        };
+        self.resize_scope(selection.len(), replacement.text.len());
+        let scope = self.scope();
+        let doc = &mut self.documents[self.active];
+        doc.content.replace_range(selection.clone(), &replacement.text);
+        doc.commit_edit(EditKind::Command("Replace"));
+
+        let replaced = selection.start..selection.start + replacement.text.len();
+        let matches = query.find_all(&doc.content, scope);
+        match search::next_match(&matches, replaced.clone(), true) {
+            Some((index, _)) => doc.select_bytes(matches[index].clone()),
+            None => doc.select_bytes(replaced),
+        }
+    }
+
+    // Collects the replacements for the preview window; nothing changes until it is confirmed
+    fn preview_replace_all(&mut self) {
+        if self.doc().read_only {
+            self.notifications.warn(format!("{} is read-only", self.doc().display_name()));
+            return;
+        }
+        let Ok(Some(query)) = self.query() else {
+            self.show_find_replace = true;
+            return;
+        };
+        self.prefs.search_history.remember_find(&self.find_text);
+        self.prefs.search_history.remember_replace(&self.replace_text);
+        let doc = self.doc();
+        let replacements = query.replacements(&doc.content, &self.replace_text, self.scope());
+        if replacements.is_empty() {
+            self.notifications.info(format!("Cannot find \"{}\"", self.find_text));
+            return;
+        }
+        self.replace_preview = Some(ReplacePreview {
+            document: doc.id,
+            content: doc.content.clone(),
+            items: search::preview(&doc.content, replacements),
+        });
+    }
+
+    // Makes the checked replacements as a single undo step. An empty replacement deletes the matches.
+    fn apply_replace_preview(&mut self, preview: ReplacePreview) {
+        let Some(index) = self.index_of(preview.document) else {
+            return;
+        };
+        if self.documents[index].content != preview.content {
+            self.notifications.warn("The document changed since the preview was made; nothing was replaced");
+            return;
+        }
+        let replacements: Vec<_> = preview.items.into_iter().filter(|i| i.checked).map(|i| i.replacement).collect();
+        if replacements.is_empty() {
+            return;
+        }
+        let removed: usize = replacements.iter().map(|r| r.range.len()).sum();
+        let inserted: usize = replacements.iter().map(|r| r.text.len()).sum();
+        self.active = index;
+        self.resize_scope(removed, inserted);
+
+        let doc = &mut self.documents[index];
+        doc.content = search::apply(&doc.content, &replacements);
+        doc.commit_edit(EditKind::Command("Replace All"));
+        // The text around the cursor may have shrunk
+        let cursor = doc.cursor.min(doc.content.chars().count());
+        doc.set_cursor(cursor);
+        self.notifications.info(if replacements.len() == 1 {
+            "Replaced 1 occurrence".to_owned()
+        } else {
+            format!("Replaced {} occurrences", replacements.len())
+        });
+    }
+
+    fn show_find_in_files(&mut self) {
+        // Start in the folder of the current file
+        if self.find_in_files.folder.is_empty() {
+            let folder = match self.doc().path.as_deref().and_then(|p| p.parent()) {
+                Some(parent) => Some(parent.to_path_buf()),
+                None => std::env::current_dir().ok(),
+            };
+            if let Some(folder) = folder {
+                self.find_in_files.folder = folder.display().to_string();
+            }
+        }
+        if self.find_in_files.find_text.is_empty() {
+            self.find_in_files.find_text.clone_from(&self.find_text);
+        }
+        self.find_in_files.show = true;
+    }
+
+    fn handle_find_in_files(&mut self, ctx: &egui::Context, action: find_in_files::Action) {
+        match action {
+            find_in_files::Action::Search => {
+                self.prefs.search_history.remember_find(&self.find_in_files.find_text);
+                // Open documents are searched as they are in the editor, unsaved changes included
+                let open = self
+                    .documents
+                    .iter()
+                    .filter_map(|d| Some((d.path.clone()?, d.content.clone())))
+                    .collect();
+                self.find_in_files.start(ctx, open);
+            }
+            find_in_files::Action::Open { path, line, column, length } => {
+                if let Some(index) = self.open_path(path) {
+                    let doc = &mut self.documents[index];
+                    let start = doc.char_index(line, column);
+                    doc.pending_selection = Some((start, start + length));
+                }
+            }
+            find_in_files::Action::Replace { query, replacement } => {
+                self.prefs.search_history.remember_replace(&replacement);
+                self.replace_in_files(&query, &replacement);
+            }
+        }
+    }
+
+    // Open files are changed in their buffers (and can be undone); the rest are rewritten on disk
+    // Acts on the search that produced the results, not on whatever is in the fields now
+    fn replace_in_files(&mut self, query: &Query, replace_text: &str) {
+        let paths: Vec<PathBuf> = self.find_in_files.results.iter().map(|f| f.path.clone()).collect();
+        let (mut files, mut count) = (0, 0);
+        for path in paths {
+            if let Some(doc) = self.documents.iter_mut().find(|d| d.path.as_ref() == Some(&path)) {
+                if doc.read_only {
+                    self.notifications.warn(format!("{} is read-only", doc.display_name()));
+                    continue;
+                }
+                let replacements = query.replacements(&doc.content, replace_text, None);
+                if !replacements.is_empty() {
+                    doc.content = search::apply(&doc.content, &replacements);
+                    doc.commit_edit(EditKind::Command("Replace in Files"));
+                    files += 1;
+                    count += replacements.len();
+                }
+                continue;
+            }
+            match replace_in_file(&path, query, replace_text, self.prefs.backup) {
+                Ok(0) => {}
+                Ok(replaced) => {
+                    files += 1;
+                    count += replaced;
+                }
+                Err(e) => self.notifications.error(format!("Failed to replace in {}: {}", path.display(), e)),
+            }
+        }
+        self.find_in_files.clear_results();
+        self.notifications.info(format!("Replaced {} occurrences in {} files", count, files));
+    }
+
+    fn show_replace_preview(&mut self, ctx: &egui::Context) {
+        let Some(preview) = &mut self.replace_preview else {
+            return;
+        };
+        let mut open = true;
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new("Replace All")
+            .open(&mut open)
+            .default_size([560.0, 360.0])
+            .show(ctx, |ui| {
+                let checked = preview.items.iter().filter(|i| i.checked).count();
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} of {} replacements selected", checked, preview.items.len()));
+                    if ui.small_button("All").clicked() {
+                        preview.items.iter_mut().for_each(|i| i.checked = true);
+                    }
+                    if ui.small_button("None").clicked() {
+                        preview.items.iter_mut().for_each(|i| i.checked = false);
+                    }
+                });
+                ui.separator();
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace).max(ui.spacing().interact_size.y);
+                egui::ScrollArea::both()
+                    .max_height(ui.available_height() - 40.0)
+                    .auto_shrink([false, true])
+                    .show_rows(ui, row_height, preview.items.len(), |ui, rows| {
+                        for item in &mut preview.items[rows] {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut item.checked, "");
+                                ui.monospace(format!("{:>5}:", item.line));
+                                ui.label(search::preview_job(ui, &preview.content, &item.replacement));
+                            });
+                        }
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(checked > 0, egui::Button::new(format!("Replace {}", checked))).clicked() {
+                        apply = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        if apply && let Some(preview) = self.replace_preview.take() {
+            self.apply_replace_preview(preview);
+        }
+        if !open || cancel {
+            self.replace_preview = None;
+        }
+    }
+}
+
+impl eframe::App for RpadApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if let Ok(serialized) = serde_json::to_string(&self.prefs) {
+            storage.set_string(eframe::APP_KEY, serialized);
+        }
+        if !self.persist_session {
+            return;
+        }
+        let session = Session::capture(&self.documents, self.active);
+        if let Err(e) = session.write_recovery(&self.documents, &mut self.recovery_files) {
+            self.notifications.warn(format!("Failed to write the recovery store: {}", e));
+        }
+        if let Ok(serialized) = serde_json::to_string(&session) {
+            storage.set_string(SESSION_KEY, serialized);
+        }
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        swap::remove_own();
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // Set window title
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(self.get_title()));
+
+        self.apply_theme(ctx, frame.info().system_theme);
+        let missing = self.fonts.update(ctx, self.prefs.editor_font.as_deref(), self.prefs.interface_font.as_deref());
+        for name in missing {
+            self.notifications.warn(format!("The font \"{}\" isn't installed; using the default instead", name));
+        }
+
+        // Intercept the window close button while there are unsaved changes
+        if ctx.input(|i| i.viewport().close_requested())
+            && !self.allow_close
+            && self.documents.iter().any(|d| d.is_modified)
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.request_exit(ctx);
+        }
+
+        // --wait: everything we were waiting on has been closed
+        if self.wait_for.as_ref().is_some_and(|ids| ids.is_empty()) {
+            self.wait_for = None;
+            self.request_exit(ctx);
+        }
+
+        // Files changed by other programs
+        self.check_external_changes();
+
+        // Periodic crash-safety copies of modified buffers
+        if self.last_autosave.elapsed() >= swap::AUTOSAVE_INTERVAL {
+            self.autosave();
+        }
+        ctx.request_repaint_after(swap::AUTOSAVE_INTERVAL);
+
+        // Pasted text may bring its own line endings; buffers only ever hold '\n'
+        ctx.input_mut(|i| {
+            for event in &mut i.events {
+                if let egui::Event::Paste(text) = event
+                    && text.contains('\r')
+                {
+                    *text = line_ending::normalize(text).into_owned();
+                }
+            }
+        });
+
+        // Bound keys are taken out of the input up front, so e.g. Ctrl+Tab doesn't reach the editor
+        // and undo is ours rather than the editor widget's (which wouldn't survive Replace All)
+        let commands = ctx.input_mut(|i| self.keymap.dispatch(i));
+        for command in commands {
+            self.run(ctx, command);
+        }
+        // Ctrl+wheel and pinch zoom the editor
+        let zoom_delta = ctx.input(|i| i.zoom_delta());
+        if zoom_delta != 1.0 {
+            self.set_zoom(self.zoom() * zoom_delta);
+        }
+        ctx.set_zoom_factor(self.prefs.ui_scale);
+
+        // Menu bar
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    self.menu_item(ui, Command::NewFile, true);
+                    self.menu_item(ui, Command::OpenFile, true);
+                    ui.separator();
+                    self.menu_item(ui, Command::Save, true);
+                    self.menu_item(ui, Command::SaveAs, true);
+                    self.encoding_menus(ui);
+                    ui.separator();
+                    self.menu_item(ui, Command::CloseTab, true);
+                    self.menu_item(ui, Command::ReopenClosedTab, !self.closed_tabs.is_empty());
+                    ui.separator();
+                    ui.menu_button("Backup on Save", |ui| {
+                        ui.radio_value(&mut self.prefs.backup, BackupMode::None, "None");
+                        ui.radio_value(&mut self.prefs.backup, BackupMode::Tilde, "Keep file~");
+                        ui.radio_value(&mut self.prefs.backup, BackupMode::Numbered, "Numbered .bak");
+                    });
+                    ui.separator();
+                    self.menu_item(ui, Command::Exit, true);
+                });
+
+                ui.menu_button("Edit", |ui| {
+                    let editable = !self.doc().read_only;
+                    self.menu_item(ui, Command::Undo, editable && self.doc().history.can_undo());
+                    self.menu_item(ui, Command::Redo, editable && self.doc().history.can_redo());
+                    self.menu_item(ui, Command::UndoHistory, true);
+                    ui.separator();
+                    ui.checkbox(&mut self.documents[self.active].read_only, "Read Only");
+                    ui.separator();
+                    self.menu_item(ui, Command::FindReplace, true);
+                    self.menu_item(ui, Command::FindInFiles, true);
+                    self.menu_item(ui, Command::GoTo, true);
+                    self.menu_item(ui, Command::FindNext, true);
+                    self.menu_item(ui, Command::FindPrevious, true);
+                });
+
+                ui.menu_button("Format", |ui| {
+                    ui.checkbox(&mut self.prefs.word_wrap, self.keymap.label(Command::WordWrap));
+                    ui.separator();
+                    ui.menu_button("Line Endings", |ui| self.line_ending_menu(ui));
+                    ui.menu_button("Indentation", |ui| self.indent_menu(ui));
+                    ui.separator();
+                    ui.label("Font Size:");
+                    ui.add(egui::Slider::new(&mut self.prefs.font_size, 8.0..=32.0));
+                    self.menu_item(ui, Command::Font, true);
+                });
+
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.prefs.status_bar, self.keymap.label(Command::StatusBar));
+                    ui.checkbox(&mut self.prefs.line_numbers, self.keymap.label(Command::LineNumbers));
+                    ui.checkbox(&mut self.notifications.show_log, "Message Log");
+                    ui.menu_button("Zoom", |ui| self.zoom_menu(ui));
+                    ui.horizontal(|ui| {
+                        ui.label("Interface Scale:");
+                        ui.add(egui::Slider::new(&mut self.prefs.ui_scale, 0.5..=3.0).step_by(0.25));
+                    });
+                    ui.separator();
+                    self.menu_item(ui, Command::Theme, true);
+                });
+
+                ui.menu_button("Help", |ui| {
+                    self.menu_item(ui, Command::KeyboardShortcuts, true);
+                    ui.separator();
+                    if ui.button("About rpad").clicked() {
+                        self.show_about = true;
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+
+        // Tab strip
+        egui::TopBottomPanel::top("tab_bar").show(ctx, |ui| {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let mut close = None;
+                    for (index, doc) in self.documents.iter().enumerate() {
+                        let label = ui.selectable_label(index == self.active, doc.tab_label());
+                        if label.clicked() {
+                            self.active = index;
+                        }
+                        if label.middle_clicked() || ui.small_button("x").clicked() {
+                            close = Some(index);
+                        }
+                        ui.separator();
+                    }
+                    if ui.small_button("+").clicked() {
+                        self.new_file();
+                    }
+                    if let Some(index) = close {
+                        self.request_close_tab(index);
+                    }
+                });
+            });
+        });
+
+        // Find & Replace dialog
+        if self.show_find_replace {
+            egui::Window::new("Find & Replace")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let mut search_forward = None;
+                    ui.horizontal(|ui| {
+                        ui.label("Find:");
+                        let response = search::history_field(ui, &mut self.find_text, &self.prefs.search_history.find);
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            search_forward = Some(true);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Replace:");
+                        search::history_field(ui, &mut self.replace_text, &self.prefs.search_history.replace);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.prefs.search.match_case, "Match Case");
+                        ui.checkbox(&mut self.prefs.search.whole_word, "Whole Word");
+                        ui.checkbox(&mut self.prefs.search.regex, "Regular Expression")
+                            .on_hover_text("Use $1 or ${name} in the replacement for capture groups, and \\n or \\t for newlines and tabs");
+                    });
+                    ui.horizontal(|ui| {
+                        let mut in_selection = self.scope().is_some();
+                        if ui.checkbox(&mut in_selection, "In selection only").changed() {
+                            self.set_selection_scope(in_selection);
+                        }
+                        search::presets_menu(
+                            ui,
+                            &mut self.prefs.search_history,
+                            &mut self.preset_name,
+                            PresetTarget {
+                                find: &mut self.find_text,
+                                replace: &mut self.replace_text,
+                                options: &mut self.prefs.search,
+                            },
+                        );
+                    });
+                    match self.query() {
+                        Ok(Some(query)) => {
+                            ui.label(self.match_count(&query));
+                        }
+                        Ok(None) => {}
+                        Err(message) => {
+                            ui.colored_label(ui.visuals().error_fg_color, message);
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Find Next").clicked() {
+                            search_forward = Some(true);
+                        }
+                        if ui.button("Find Previous").clicked() {
+                            search_forward = Some(false);
+                        }
+                        if ui.button("Replace").clicked() {
+                            self.replace_next();
+                        }
+                        if ui.button("Replace All...").clicked() {
+                            self.preview_replace_all();
+                        }
+                        if ui.button("Close").clicked() {
+                            self.show_find_replace = false;
+                        }
+                    });
+                    if let Some(forward) = search_forward {
+                        self.find_next(forward);
+                    }
+                });
+        }
+
+        // Go To dialog
+        if self.show_goto {
+            self.show_goto_dialog(ctx);
+        }
+
+        // Theme settings
+        if self.theme_dialog.is_some() {
+            self.show_theme_dialog(ctx);
+        }
+
+        // Font settings
+        if self.show_font_dialog {
+            self.show_font_dialog(ctx);
+        }
+
+        if self.show_shortcuts {
+            self.show_shortcuts_window(ctx);
+        }
+
+        // Find in Files
+        if self.find_in_files.show
+            && let Some(action) = self.find_in_files.show(ctx, &mut self.prefs.search_history)
+        {
+            self.handle_find_in_files(ctx, action);
+        }
+
+        // Replace All preview
+        self.show_replace_preview(ctx);
+
+        // Unsaved changes dialog
+        if let Some(action) = self.pending_action {
+            match self.pending_target(action) {
+                Some(index) => {
+                    let filename = self.documents[index].display_name().to_owned();
+                    egui::Window::new("rpad")
+                        .collapsible(false)
+                        .resizable(false)
+                        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                        .show(ctx, |ui| {
+                            ui.label(format!("Do you want to save changes to {}?", filename));
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                if ui.button("Save").clicked() {
+                                    self.resolve_pending(ctx, action, true);
+                                }
+                                if ui.button("Don't Save").clicked() {
+                                    self.resolve_pending(ctx, action, false);
+                                }
+                                if ui.button("Cancel").clicked() {
+                                    self.pending_action = None;
+                                    self.exit_discarded.clear();
+                                }
+                            });
+                        });
+                }
+                None => self.pending_action = None,
+            }
+        }
+
+        // Recovery dialog
+        if !self.recoveries.is_empty() {
+            self.show_recovery_dialog(ctx);
+        }
+
+        // External change dialog
+        if !self.external_changes.is_empty() {
+            self.show_external_change_dialog(ctx);
+        }
+
+        // Save failure dialog
+        if self.save_failure.is_some() {
+            self.show_save_failure_dialog(ctx);
+        }
+
+        // Notifications
+        self.notifications.show_toasts(ctx);
+        if self.notifications.show_log {
+            self.notifications.show_log(ctx);
+        }
+
+        // Undo history
+        if self.show_undo_history {
+            self.show_undo_history(ctx);
+        }
+
+        // About dialog
+        if self.show_about {
+            egui::Window::new("About rpad")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.heading("rpad - Basic GUI based text editor");
+                        ui.label("Version 1.0");
+                        ui.label("Built with Rust and egui by Dr. Suresh Ramasamy (https://github.com/sureshdr/rpad)");
+                        ui.separator();
+                        if ui.button("OK").clicked() {
+                            self.show_about = false;
+                        }
+                    });
+                });
+        }
+
+        // Status bar; each segment opens whatever changes it
+        if self.prefs.status_bar {
+            egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let goto_hint = match self.keymap.shortcut(Command::GoTo) {
+                        Some(shortcut) => format!("Go To ({})", shortcut),
+                        None => "Go To".to_owned(),
+                    };
+                    let doc = &mut self.documents[self.active];
+                    let (line, column) = doc.line_column(doc.cursor);
+                    let (lines, chars) = doc.totals();
+                    let mut goto = status_segment(ui, format!("Ln {}, Col {}", line, column))
+                        .on_hover_text(&goto_hint)
+                        .clicked();
+                    if let Some(selection) = doc.selection_stats() {
+                        ui.separator();
+                        ui.label(format!(
+                            "{} selected ({} {}, {} {})",
+                            selection.chars,
+                            selection.lines,
+                            if selection.lines == 1 { "line" } else { "lines" },
+                            selection.words,
+                            if selection.words == 1 { "word" } else { "words" },
+                        ));
+                    }
+                    ui.separator();
+                    goto |= status_segment(ui, format!("{} lines, {} characters", lines, chars))
+                        .on_hover_text(goto_hint)
+                        .clicked();
+                    if goto {
+                        self.open_goto();
+                    }
+                    if let Some(pending) = self.keymap.pending() {
+                        ui.separator();
+                        ui.label(format!("({}) was pressed. Waiting for the next key...", pending));
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let doc = &self.documents[self.active];
+                        if doc.is_modified {
+                            ui.label("Modified");
+                        } else {
+                            ui.label("Ready");
+                        }
+                        if doc.read_only {
+                            ui.separator();
+                            ui.label("Read Only");
+                        }
+                        ui.separator();
+                        let zoom = (self.zoom() * 100.0).round();
+                        ui.menu_button(format!("{}%", zoom), |ui| self.zoom_menu(ui));
+                        ui.separator();
+                        ui.menu_button(self.doc().indent.label(), |ui| self.indent_menu(ui));
+                        ui.separator();
+                        let doc = self.doc_mut();
+                        let language = doc.highlighting.language(doc.path.as_deref(), &doc.content);
+                        ui.menu_button(language, |ui| self.language_menu(ui));
+                        ui.separator();
+                        ui.menu_button(self.doc().encoding.label(), |ui| self.encoding_menus(ui));
+                        ui.separator();
+                        ui.menu_button(self.doc().line_ending.label(), |ui| self.line_ending_menu(ui));
+                        let doc = self.doc();
+                        if doc.mixed_line_endings {
+                            ui.colored_label(ui.visuals().warn_fg_color, "Mixed line endings")
+                                .on_hover_text(format!("Saving will convert every line ending to {}", doc.line_ending.label()));
+                        }
+                    });
+                });
+            });
+        }
+
+        // Tab inserts spaces in documents indented with spaces
+        let doc = self.doc();
+        if matches!(doc.indent, Indent::Spaces(_))
+            && !doc.read_only
+            && ctx.memory(|m| m.has_focus(doc.editor_id()))
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Tab))
+        {
+            self.doc_mut().insert_indent();
+        }
+
+        // Main text editor
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let font_size = self.prefs.font_size * self.zoom();
+            let word_wrap = self.prefs.word_wrap;
+            let line_numbers = self.prefs.line_numbers;
+            // Matches are highlighted while the Find window is open
+            let query = if self.show_find_replace { self.query().ok().flatten() } else { None };
+            let scope = self.scope();
+
+            let doc = &mut self.documents[self.active];
+            let editor_id = doc.editor_id();
+
+            let mut scroll_area = if word_wrap {
+                egui::ScrollArea::vertical()
+            } else {
+                egui::ScrollArea::both()
+            }
+            .id_source(doc.id)
+            .auto_shrink([false; 2]);
+
+            // Put back the scroll position remembered in the session
+            let restoring = std::mem::take(&mut doc.restore_view);
+            if restoring {
+                scroll_area = scroll_area.scroll_offset(doc.scroll);
+            }
+
+            // Apply a selection requested from elsewhere (session, command line, search)
+            let pending = doc.pending_selection.take().map(|(anchor, cursor)| {
+                let chars = doc.content.chars().count();
+                let (anchor, cursor) = (anchor.min(chars), cursor.min(chars));
+                select_in_editor(ctx, editor_id, anchor, cursor);
+                egui::text::CCursor::new(cursor)
+            });
+
+            let read_only = doc.read_only;
+            let font_id = egui::FontId::monospace(font_size);
+            let (current_line, _) = doc.line_column(doc.cursor);
+            let gutter_width = if line_numbers { gutter::width(ui, &font_id, doc.totals().0) } else { 0.0 };
+
+            // Taken out for the layouter, which the editor calls while it holds the text
+            let language = doc.highlighting.language(doc.path.as_deref(), &doc.content);
+            let theme = &self.theme;
+            let syntax_theme = &self.syntax_theme;
+            let mut highlighting = std::mem::take(&mut doc.highlighting);
+            let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
+                let format = egui::TextFormat {
+                    font_id: egui::FontId::monospace(font_size),
+                    color: theme.text.0,
+                    ..Default::default()
+                };
+                let matches = query.as_ref().map(|q| q.find_all(string, scope.clone())).unwrap_or_default();
+                let match_background = theme.search_match.0;
+                let mut layout_job = highlighting.layout_job(string, language, syntax_theme, format, &matches, match_background);
+
+                if word_wrap {
+                    layout_job.wrap.max_width = wrap_width;
+                }
+
+                ui.fonts(|f| f.layout_job(layout_job))
+            };
+            let output = ui
+                .horizontal_top(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    // Outside the scroll area, so it stays put when scrolling sideways
+                    let gutter = line_numbers.then(|| {
+                        ui.allocate_exact_size(egui::vec2(gutter_width, ui.available_height()), egui::Sense::click_and_drag())
+                    });
+                    let output = scroll_area.show(ui, |ui| {
+                        // Reserved so the current line can be painted behind the text
+                        let background = ui.painter().add(egui::Shape::Noop);
+                        // A &str buffer keeps the text selectable and copyable but not editable
+                        let mut read_only_text = doc.content.as_str();
+                        let text: &mut dyn egui::TextBuffer = if read_only {
+                            &mut read_only_text
+                        } else {
+                            &mut doc.content
+                        };
+                        let output = egui::TextEdit::multiline(text)
+                            .id(editor_id)
+                            .font(egui::TextStyle::Monospace)
+                            .code_editor()
+                            .frame(false)
+                            .desired_width(f32::INFINITY)
+                            .min_size(ui.available_size())
+                            .layouter(&mut layouter)
+                            .show(ui);
+
+                        let area = output.response.rect;
+                        let fill = if read_only { egui::Color32::TRANSPARENT } else { theme.background.0 };
+                        ui.painter().set(
+                            background,
+                            vec![
+                                egui::Shape::rect_filled(area, 0.0, fill),
+                                gutter::current_line_shape(theme, &output.galley, output.text_draw_pos, current_line - 1, area),
+                            ],
+                        );
+
+                        // The editor only follows the cursor when the user moves it, so do it here
+                        if let Some(cursor) = pending
+                            && !restoring
+                        {
+                            let rect = output.galley.pos_from_cursor(&output.galley.from_ccursor(cursor));
+                            ui.scroll_to_rect(rect.translate(output.text_draw_pos.to_vec2()), Some(egui::Align::Center));
+                        }
+                        output
+                    });
+
+                    if let Some((rect, response)) = gutter {
+                        let galley = &output.inner.galley;
+                        let text_pos = output.inner.text_draw_pos;
+                        gutter::paint(ui, theme, rect, galley, text_pos, current_line - 1, &font_id);
+
+                        // Click selects a line, dragging selects every line passed over
+                        if let Some(pointer) = response.interact_pointer_pos() {
+                            let line = gutter::line_at(galley, text_pos, pointer.y);
+                            if response.drag_started() || response.clicked() || self.gutter_drag.is_none() {
+                                self.gutter_drag = Some(line);
+                            }
+                            let anchor = self.gutter_drag.unwrap_or(line);
+                            let (first, last) = (anchor.min(line), anchor.max(line));
+                            let start = doc.char_index(first + 1, 1);
+                            let end = doc.char_index(last + 2, 1);
+                            // Not through pending_selection, which would scroll on every frame of the drag
+                            if line < anchor {
+                                select_in_editor(ctx, editor_id, end, start);
+                            } else {
+                                select_in_editor(ctx, editor_id, start, end);
+                            }
+                        } else {
+                            self.gutter_drag = None;
+                        }
+                    }
+                    output
+                })
+                .inner;
+
+            if !highlighting.is_complete() {
+                ctx.request_repaint();
+            }
+            doc.highlighting = highlighting;
+            doc.scroll = output.state.offset;
+            if let Some(cursor_range) = output.inner.cursor_range {
+                doc.cursor = cursor_range.primary.ccursor.index;
+                doc.anchor = cursor_range.secondary.ccursor.index;
+            }
+            if output.inner.response.changed() {
+                doc.commit_edit(EditKind::Typing);
+                // Typing moves text out from under "In selection only", so drop it rather than search the wrong range
+                if self.search_scope.as_ref().is_some_and(|scope| scope.document == doc.id) {
+                    self.search_scope = None;
+                }
+            }
+        });
+    }
+}
+
+fn malformed_warning(doc: &Document) -> String {
+    format!(
+        "{} isn't valid {}, so it was opened read-only; saving would replace the bytes it couldn't decode",
+        doc.display_name(),
+        doc.encoding.label()
+    )
+}
+
+// Rewrites a file that isn't open, keeping its encoding and line ending. Returns the number of replacements.
+fn replace_in_file(path: &std::path::Path, query: &Query, replacement: &str, backup: BackupMode) -> io::Result<usize> {
+    let mut doc = Document::open(path.to_path_buf())?;
+    let replacements = query.replacements(&doc.content, replacement, None);
+    if replacements.is_empty() {
+        return Ok(0);
+    }
+    // Saving would also replace bytes that didn't decode
+    if doc.malformed {
+        return Err(io::Error::other(format!("it isn't valid {}; open it to replace there", doc.encoding.label())));
+    }
+    // Saving would convert every line ending, not just change the matches
+    if doc.mixed_line_endings {
+        return Err(io::Error::other("it has mixed line endings; open it to replace there"));
+    }
+    doc.content = search::apply(&doc.content, &replacements);
+    fileio::write_atomic(path, &doc.encode()?, backup)?;
+    Ok(replacements.len())
+}
+
+// Latin, look-alike characters, CJK and emoji, to check nothing falls back to boxes
+const FONT_SAMPLE: &str = "The quick brown fox 0O 1lI {}[] 日本語 中文 한국어 😀";
+
+// None is the default font, shown as `default`
+fn font_combo(ui: &mut egui::Ui, id: &str, choice: &mut Option<String>, default: &str, fonts: &[String]) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(choice.as_deref().unwrap_or(default))
+        .width(220.0)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(choice, None, default);
+            for name in fonts {
+                ui.selectable_value(choice, Some(name.clone()), name);
+            }
+        });
+}
+
+const THEME_PREVIEW: &str = "// Sum the values
+fn total(values: &[f64]) -> f64 {
+    let mut total = 0.0;
+    for value in values {
+        total += value * 2.5;
+    }
+    println!(\"total: {}\", total);
+    total
+}";
+
+// A snippet drawn the way the editor would draw it in `theme`, with the current line and a search match
+fn theme_preview(ui: &mut egui::Ui, theme: &Theme, syntax_theme: &SyntaxTheme, highlighting: &mut Highlighting) {
+    let font_id = egui::FontId::monospace(13.0);
+    let format = egui::TextFormat {
+        font_id: font_id.clone(),
+        color: theme.text.0,
+        ..Default::default()
+    };
+    let matches: Vec<Range<usize>> = THEME_PREVIEW
+        .match_indices("values")
+        .map(|(start, m)| start..start + m.len())
+        .collect();
+    let job = highlighting.layout_job(THEME_PREVIEW, "Rust", syntax_theme, format, &matches, theme.search_match.0);
+    let galley = ui.fonts(|f| f.layout_job(job));
+
+    let margin = 6.0;
+    let current_line = 2;
+    let gutter_width = gutter::width(ui, &font_id, THEME_PREVIEW.lines().count());
+    let size = egui::vec2(gutter_width + galley.size().x + 4.0 * margin, galley.size().y + 2.0 * margin);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let gutter_rect = egui::Rect::from_min_size(rect.min, egui::vec2(gutter_width, rect.height()));
+    let text_area = egui::Rect::from_min_max(egui::pos2(gutter_rect.right(), rect.top()), rect.max);
+    let text_pos = text_area.min + egui::vec2(margin, margin);
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(text_area, 0.0, theme.background.0);
+    painter.add(gutter::current_line_shape(theme, &galley, text_pos, current_line, text_area));
+    painter.galley(text_pos, galley.clone());
+    gutter::paint(ui, theme, gutter_rect, &galley, text_pos, current_line, &font_id);
+}
+
+// Sets the editor's selection for the next frame and focuses it
+fn select_in_editor(ctx: &egui::Context, editor_id: egui::Id, anchor: usize, cursor: usize) {
+    let mut state = egui::TextEdit::load_state(ctx, editor_id).unwrap_or_default();
+    let range = egui::text::CCursorRange::two(egui::text::CCursor::new(anchor), egui::text::CCursor::new(cursor));
+    state.set_ccursor_range(Some(range));
+    egui::TextEdit::store_state(ctx, editor_id, state);
+    ctx.memory_mut(|mem| mem.request_focus(editor_id));
+}
+
+// A status bar label that can be clicked
+fn status_segment(ui: &mut egui::Ui, text: String) -> egui::Response {
+    ui.add(egui::Button::new(text).frame(false))
+}
+
+// Failures that retrying elsewhere (Save As) can work around, so they get a dialog rather than a toast
+fn is_critical(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::StorageFull | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+fn format_age(age: std::time::Duration) -> String {
+    match age.as_secs() {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s => format!("{}h ago", s / 3600),
+    }
+}
@@ -0,0 +1,193 @@
+// src/cli.rs
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: rpad [OPTIONS] [FILE[:LINE[:COLUMN]]]... [-]
+
+Arguments:
+  FILE[:LINE[:COLUMN]]  Open FILE, optionally placing the cursor at LINE and COLUMN
+  -                     Read standard input into a new buffer
+
+Options:
+      --new-window  Open only the given files, without restoring or saving the session
+      --readonly    Open the given files read-only
+      --wait        Exit once the given files have been closed (for use as $EDITOR);
+                    implies --new-window
+  -h, --help        Print this help
+  -V, --version     Print the version";
+
+#[derive(Default)]
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub stdin: bool,
+    pub new_window: bool,
+    pub readonly: bool,
+    pub wait: bool,
+}
+
+pub struct FileArg {
+    pub path: PathBuf,
+    // 1-based, as printed by compilers
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+pub enum Command {
+    Run(Args),
+    Help,
+    Version,
+}
+
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, String> {
+    let mut parsed = Args::default();
+    let mut options_done = false;
+    for arg in args {
+        if options_done {
+            parsed.files.push(FileArg::parse(arg));
+            continue;
+        }
+        match arg.to_str() {
+            Some("--") => options_done = true,
+            Some("-") => parsed.stdin = true,
+            Some("--new-window") => parsed.new_window = true,
+            Some("--readonly") => parsed.readonly = true,
+            Some("--wait") => parsed.wait = true,
+            Some("-h" | "--help") => return Ok(Command::Help),
+            Some("-V" | "--version") => return Ok(Command::Version),
+            Some(option) if option.starts_with('-') => {
+                return Err(format!("unknown option '{}'\n\n{}", option, USAGE));
+            }
+            _ => parsed.files.push(FileArg::parse(arg)),
+        }
+    }
+    if parsed.wait {
+        parsed.new_window = true;
+    }
+    Ok(Command::Run(parsed))
+}
+
+impl FileArg {
+    fn parse(arg: OsString) -> Self {
+        let path = PathBuf::from(&arg);
+        // A file that really exists wins, so names containing ':' (or C:\ paths) still work
+        if path.exists() {
+            return Self::plain(path);
+        }
+        let Some(text) = arg.to_str() else {
+            return Self::plain(path);
+        };
+
+        let mut parts = text.rsplitn(3, ':');
+        let last = parts.next().and_then(parse_position);
+        let middle = parts.next();
+        let rest = parts.next();
+        match (rest, middle, last) {
+            (Some(file), Some(line), Some(column)) if !file.is_empty() => match parse_position(line) {
+                Some(line) => Self {
+                    path: PathBuf::from(file),
+                    line: Some(line),
+                    column: Some(column),
+                },
+                None => Self {
+                    path: PathBuf::from(format!("{}:{}", file, line)),
+                    line: Some(column),
+                    column: None,
+                },
+            },
+            (None, Some(file), Some(line)) if !file.is_empty() => Self {
+                path: PathBuf::from(file),
+                line: Some(line),
+                column: None,
+            },
+            _ => Self::plain(path),
+        }
+    }
+
+    fn plain(path: PathBuf) -> Self {
+        Self {
+            path,
+            line: None,
+            column: None,
+        }
+    }
+}
+
+fn parse_position(text: &str) -> Option<usize> {
+    text.parse().ok().filter(|&n| n > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn file(arg: &str) -> FileArg {
+        FileArg::parse(OsString::from(arg))
+    }
+
+    fn run(args: &[&str]) -> Args {
+        match parse(args.iter().map(OsString::from)) {
+            Ok(Command::Run(args)) => args,
+            _ => panic!("{:?} didn't parse as a run", args),
+        }
+    }
+
+    #[test]
+    fn file_with_line() {
+        let arg = file("src/no-such-file.rs:12");
+        assert_eq!(arg.path, PathBuf::from("src/no-such-file.rs"));
+        assert_eq!((arg.line, arg.column), (Some(12), None));
+    }
+
+    #[test]
+    fn file_with_line_and_column() {
+        let arg = file("src/no-such-file.rs:12:5");
+        assert_eq!(arg.path, PathBuf::from("src/no-such-file.rs"));
+        assert_eq!((arg.line, arg.column), (Some(12), Some(5)));
+    }
+
+    #[test]
+    fn colon_in_name() {
+        // Not a position, so it stays part of the name
+        let arg = file("notes:draft");
+        assert_eq!(arg.path, PathBuf::from("notes:draft"));
+        assert_eq!((arg.line, arg.column), (None, None));
+
+        let arg = file("notes:draft:7");
+        assert_eq!(arg.path, PathBuf::from("notes:draft"));
+        assert_eq!((arg.line, arg.column), (Some(7), None));
+
+        // Line 0 isn't a line
+        let arg = file("notes:0");
+        assert_eq!(arg.path, PathBuf::from("notes:0"));
+        assert_eq!(arg.line, None);
+    }
+
+    #[test]
+    fn existing_file_wins() {
+        let path = std::env::temp_dir().join(format!("rpad-cli-{}:3", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let arg = file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(arg.path, path);
+        assert_eq!(arg.line, None);
+    }
+
+    #[test]
+    fn dash_dash_ends_options() {
+        let args = run(&["--readonly", "--", "-file", "--wait"]);
+        assert!(args.readonly && !args.wait);
+        let paths: Vec<_> = args.files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("-file"), PathBuf::from("--wait")]);
+
+        assert!(parse([OsString::from("-file")]).is_err());
+    }
+
+    #[test]
+    fn wait_implies_new_window() {
+        let args = run(&["--wait", "a.txt", "-"]);
+        assert!(args.wait && args.new_window && args.stdin);
+        assert_eq!(args.files.len(), 1);
+    }
+}
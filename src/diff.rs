@@ -0,0 +1,55 @@
+// src/diff.rs
+use eframe::egui;
+use similar::{ChangeTag, TextDiff};
+
+// Lines of context kept around each change
+const CONTEXT_LINES: usize = 3;
+
+pub struct DiffLine {
+    pub tag: ChangeTag,
+    pub text: String,
+}
+
+// Unified-style line diff, with unchanged stretches trimmed down to some context
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let diff = TextDiff::from_lines(old, new);
+    let mut lines = Vec::new();
+    for (index, group) in diff.grouped_ops(CONTEXT_LINES).iter().enumerate() {
+        if index > 0 {
+            lines.push(DiffLine {
+                tag: ChangeTag::Equal,
+                text: "...".to_owned(),
+            });
+        }
+        for op in group {
+            for change in diff.iter_changes(op) {
+                lines.push(DiffLine {
+                    tag: change.tag(),
+                    text: change.value().trim_end_matches(['\r', '\n']).to_owned(),
+                });
+            }
+        }
+    }
+    lines
+}
+
+pub fn show_diff(ui: &mut egui::Ui, lines: &[DiffLine]) {
+    if lines.is_empty() {
+        ui.label("No differences.");
+        return;
+    }
+    egui::ScrollArea::both().max_height(300.0).show(ui, |ui| {
+        for line in lines {
+            let (prefix, color) = match line.tag {
+                ChangeTag::Delete => ("-", egui::Color32::from_rgb(200, 60, 60)),
+                ChangeTag::Insert => ("+", egui::Color32::from_rgb(60, 160, 60)),
+                ChangeTag::Equal => (" ", ui.visuals().weak_text_color()),
+            };
+            ui.label(
+                egui::RichText::new(format!("{} {}", prefix, line.text))
+                    .monospace()
+                    .color(color),
+            );
+        }
+    });
+}
@@ -0,0 +1,366 @@
+// src/document.rs
+use crate::encoding::{self, TextEncoding};
+use crate::highlight::Highlighting;
+use crate::indent::{self, Indent};
+use crate::line_ending::{self, LineEnding};
+use crate::undo::{EditKind, UndoHistory};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+pub struct Document {
+    // Keeps egui widget state (cursor, scroll) separate per tab
+    pub id: u64,
+    pub content: String,
+    pub path: Option<PathBuf>,
+    pub is_modified: bool,
+    pub read_only: bool,
+    // Modification time and size of the file when we last read or wrote it
+    pub disk_stamp: Option<(SystemTime, u64)>,
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+    // Set when the file on disk mixed line endings; saving converts them all
+    pub mixed_line_endings: bool,
+    // Set when the file had bytes the encoding couldn't decode; saving would write U+FFFD in
+    // their place, so such files open read-only
+    pub malformed: bool,
+    // Character index of the cursor and scroll offset, mirrored from the editor for the session
+    pub cursor: usize,
+    // Other end of the selection; equal to `cursor` when nothing is selected
+    pub anchor: usize,
+    pub scroll: egui::Vec2,
+    // Push the scroll offset back into the editor the next time the tab is shown
+    pub restore_view: bool,
+    // Selection (anchor, cursor) to put into the editor and scroll into view on the next frame
+    pub pending_selection: Option<(usize, usize)>,
+    pub history: UndoHistory,
+    pub indent: Indent,
+    // Zoom for this tab alone, when per-document zoom is on
+    pub zoom: Option<f32>,
+    pub highlighting: Highlighting,
+    // Bumped on every change to `content`, to know when cached data is stale
+    revision: u64,
+    line_index: Option<LineIndex>,
+}
+
+// Where each line starts, rebuilt only when the text changes
+struct LineIndex {
+    revision: u64,
+    // Character index of the start of each line
+    starts: Vec<usize>,
+    chars: usize,
+}
+
+// Characters, lines and words in the selection
+pub struct SelectionStats {
+    pub chars: usize,
+    pub lines: usize,
+    pub words: usize,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            content: String::new(),
+            path: None,
+            is_modified: false,
+            read_only: false,
+            disk_stamp: None,
+            encoding: TextEncoding::default(),
+            line_ending: LineEnding::default(),
+            mixed_line_endings: false,
+            malformed: false,
+            cursor: 0,
+            anchor: 0,
+            scroll: egui::Vec2::ZERO,
+            restore_view: false,
+            pending_selection: None,
+            history: UndoHistory::default(),
+            indent: Indent::default(),
+            zoom: None,
+            highlighting: Highlighting::default(),
+            revision: 0,
+            line_index: None,
+        }
+    }
+}
+
+impl Document {
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let disk_stamp = disk_stamp(&path);
+        let bytes = fs::read(&path)?;
+        Ok(Self {
+            disk_stamp,
+            ..Self::from_bytes(Some(path), &bytes)
+        })
+    }
+
+    pub fn open_with(path: PathBuf, encoding: &'static encoding_rs::Encoding) -> io::Result<Self> {
+        let disk_stamp = disk_stamp(&path);
+        let (content, encoding, malformed) = encoding::decode_with(&fs::read(&path)?, encoding);
+        Ok(Self {
+            disk_stamp,
+            ..Self::from_text(Some(path), &content, encoding, malformed)
+        })
+    }
+
+    // Replaces the buffer with what is on disk now, keeping the cursor where it was.
+    // The reload is an undo step, so the previous text isn't lost.
+    pub fn reload(&mut self) -> io::Result<()> {
+        self.reload_with(self.encoding.encoding)
+    }
+
+    pub fn reload_with(&mut self, encoding: &'static encoding_rs::Encoding) -> io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let disk = Self::open_with(path, encoding)?;
+        self.content = disk.content;
+        self.encoding = disk.encoding;
+        self.line_ending = disk.line_ending;
+        self.mixed_line_endings = disk.mixed_line_endings;
+        // Only undo the read-only state that decoding errors put on, not one the user chose
+        if disk.malformed != self.malformed {
+            self.read_only = disk.malformed;
+        }
+        self.malformed = disk.malformed;
+        self.disk_stamp = disk.disk_stamp;
+        self.commit_edit(EditKind::Command("Reload"));
+        self.mark_saved();
+        self.set_cursor(self.cursor);
+        Ok(())
+    }
+
+    // Call after changing `content` so the change can be undone
+    pub fn commit_edit(&mut self, kind: EditKind) {
+        self.revision += 1;
+        self.history.record(&self.content, kind);
+        self.is_modified = !self.history.is_at_saved();
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.is_modified = false;
+    }
+
+    // The buffer differs from disk in a way undo can't take back (e.g. the file changed)
+    pub fn mark_unsaved(&mut self) {
+        self.history.forget_saved();
+        self.is_modified = true;
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(at) = self.history.undo(&mut self.content) {
+            self.after_history_move(at);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(at) = self.history.redo(&mut self.content) {
+            self.after_history_move(at);
+        }
+    }
+
+    pub fn jump_to_history(&mut self, node: usize) {
+        if let Some(at) = self.history.jump_to(&mut self.content, node) {
+            self.after_history_move(at);
+        }
+    }
+
+    fn after_history_move(&mut self, byte_offset: usize) {
+        self.revision += 1;
+        self.is_modified = !self.history.is_at_saved();
+        self.set_cursor(self.content[..byte_offset].chars().count());
+    }
+
+    // Whether the file on disk is no longer the one we last read or wrote
+    pub fn changed_on_disk(&self) -> bool {
+        self.path.as_deref().is_some_and(|path| disk_stamp(path) != self.disk_stamp)
+    }
+
+    // An untitled buffer holding unsaved text, e.g. from the recovery store
+    pub fn untitled(content: String) -> Self {
+        Self {
+            history: UndoHistory::new(content.clone()),
+            indent: indent::detect(&content).unwrap_or_default(),
+            content,
+            is_modified: true,
+            ..Default::default()
+        }
+    }
+
+    // Detects the encoding; used for files and for text piped in on stdin
+    pub fn from_bytes(path: Option<PathBuf>, bytes: &[u8]) -> Self {
+        let (content, encoding, malformed) = encoding::decode(bytes);
+        Self::from_text(path, &content, encoding, malformed)
+    }
+
+    fn from_text(path: Option<PathBuf>, text: &str, encoding: TextEncoding, malformed: bool) -> Self {
+        let (line_ending, mixed_line_endings) = line_ending::detect(text);
+        let content = line_ending::normalize(text).into_owned();
+        Self {
+            history: UndoHistory::new(content.clone()),
+            content,
+            // Text piped in has no original bytes to lose
+            read_only: malformed && path.is_some(),
+            path,
+            encoding,
+            malformed,
+            line_ending: line_ending.unwrap_or_default(),
+            mixed_line_endings,
+            indent: indent::detect(text).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    // The bytes to write to disk, in the document's encoding and line ending
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        self.encoding.encode(&self.line_ending.apply(&self.content))
+    }
+
+    pub fn set_cursor(&mut self, index: usize) {
+        self.pending_selection = Some((index, index));
+    }
+
+    // The selection as a byte range, in order
+    pub fn selection_bytes(&self) -> Range<usize> {
+        let start = byte_offset(&self.content, self.anchor.min(self.cursor));
+        let end = byte_offset(&self.content, self.anchor.max(self.cursor));
+        start..end
+    }
+
+    pub fn select_bytes(&mut self, range: Range<usize>) {
+        let start = self.content[..range.start].chars().count();
+        let end = start + self.content[range].chars().count();
+        self.pending_selection = Some((start, end));
+    }
+
+    // Character index of a 1-based line and column, clamped to the text
+    pub fn char_index(&mut self, line: usize, column: usize) -> usize {
+        let index = self.line_index();
+        let Some(&start) = index.starts.get(line.max(1) - 1) else {
+            return index.chars;
+        };
+        let end = index.starts.get(line.max(1)).map_or(index.chars, |&next| next - 1);
+        start + (column.max(1) - 1).min(end - start)
+    }
+
+    fn line_index(&mut self) -> &LineIndex {
+        if self.line_index.as_ref().is_none_or(|index| index.revision != self.revision) {
+            let mut starts = vec![0];
+            let mut chars = 0;
+            for c in self.content.chars() {
+                chars += 1;
+                if c == '\n' {
+                    starts.push(chars);
+                }
+            }
+            self.line_index = Some(LineIndex {
+                revision: self.revision,
+                starts,
+                chars,
+            });
+        }
+        self.line_index.as_ref().unwrap()
+    }
+
+    // Total lines and characters
+    pub fn totals(&mut self) -> (usize, usize) {
+        let index = self.line_index();
+        (index.starts.len(), index.chars)
+    }
+
+    // 1-based line and column of a character index
+    pub fn line_column(&mut self, index: usize) -> (usize, usize) {
+        let starts = &self.line_index().starts;
+        let line = starts.partition_point(|&start| start <= index).max(1);
+        (line, index - starts[line - 1] + 1)
+    }
+
+    pub fn selection_stats(&self) -> Option<SelectionStats> {
+        let range = self.selection_bytes();
+        if range.is_empty() {
+            return None;
+        }
+        let text = &self.content[range];
+        Some(SelectionStats {
+            chars: text.chars().count(),
+            lines: text.trim_end_matches('\n').matches('\n').count() + 1,
+            words: text.split_whitespace().count(),
+        })
+    }
+
+    // Replaces the selection with what Tab should insert there
+    pub fn insert_indent(&mut self) {
+        let range = self.selection_bytes();
+        let start = self.anchor.min(self.cursor);
+        let (_, column) = self.line_column(start);
+        let text = self.indent.text_at(column - 1);
+        self.content.replace_range(range, &text);
+        self.commit_edit(EditKind::Typing);
+        self.set_cursor(start + text.chars().count());
+    }
+
+    // Character index of a byte offset into the file as saved, in its encoding and line ending
+    pub fn char_index_at_file_offset(&self, offset: usize) -> usize {
+        let newline = self.encoding.encoded_len(self.line_ending.as_str());
+        let mut position = self.encoding.bom_len();
+        let mut buffer = [0; 4];
+        for (index, c) in self.content.chars().enumerate() {
+            let width = match c {
+                '\n' => newline,
+                c => self.encoding.encoded_len(c.encode_utf8(&mut buffer)),
+            };
+            if position + width > offset {
+                return index;
+            }
+            position += width;
+        }
+        self.content.chars().count()
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.line_ending != line_ending || self.mixed_line_endings {
+            self.line_ending = line_ending;
+            self.mixed_line_endings = false;
+            self.mark_unsaved();
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("Untitled")
+    }
+
+    pub fn tab_label(&self) -> String {
+        let modified = if self.is_modified { "*" } else { "" };
+        format!("{}{}", modified, self.display_name())
+    }
+
+    pub fn editor_id(&self) -> egui::Id {
+        egui::Id::new(("editor", self.id))
+    }
+
+    // An untouched "Untitled" buffer that can be replaced by an opened file
+    pub fn is_blank(&self) -> bool {
+        self.path.is_none() && !self.is_modified && self.content.is_empty()
+    }
+}
+
+pub fn disk_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn byte_offset(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), |(offset, _)| offset)
+}
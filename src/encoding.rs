@@ -0,0 +1,248 @@
+// src/encoding.rs
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+use std::io;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self::new(encoding_rs::UTF_8, false)
+    }
+}
+
+// Encodings offered by "Save with Encoding", Notepad style
+pub const SAVE_ENCODINGS: &[TextEncoding] = &[
+    TextEncoding::new(encoding_rs::UTF_8, false),
+    TextEncoding::new(encoding_rs::UTF_8, true),
+    TextEncoding::new(encoding_rs::UTF_16LE, true),
+    TextEncoding::new(encoding_rs::UTF_16BE, true),
+    TextEncoding::new(encoding_rs::WINDOWS_1252, false),
+    TextEncoding::new(encoding_rs::ISO_8859_2, false),
+    TextEncoding::new(encoding_rs::WINDOWS_1251, false),
+    TextEncoding::new(encoding_rs::KOI8_R, false),
+    TextEncoding::new(encoding_rs::SHIFT_JIS, false),
+    TextEncoding::new(encoding_rs::EUC_JP, false),
+    TextEncoding::new(encoding_rs::GBK, false),
+    TextEncoding::new(encoding_rs::BIG5, false),
+    TextEncoding::new(encoding_rs::EUC_KR, false),
+];
+
+// Encodings offered by "Reopen with Encoding"; the BOM is honoured if present
+pub const REOPEN_ENCODINGS: &[&Encoding] = &[
+    encoding_rs::UTF_8,
+    encoding_rs::UTF_16LE,
+    encoding_rs::UTF_16BE,
+    encoding_rs::WINDOWS_1252,
+    encoding_rs::ISO_8859_2,
+    encoding_rs::WINDOWS_1251,
+    encoding_rs::KOI8_R,
+    encoding_rs::SHIFT_JIS,
+    encoding_rs::EUC_JP,
+    encoding_rs::GBK,
+    encoding_rs::BIG5,
+    encoding_rs::EUC_KR,
+];
+
+impl TextEncoding {
+    pub const fn new(encoding: &'static Encoding, bom: bool) -> Self {
+        Self { encoding, bom }
+    }
+
+    pub fn label(&self) -> String {
+        let name = match self.encoding.name() {
+            "UTF-16LE" => "UTF-16 LE",
+            "UTF-16BE" => "UTF-16 BE",
+            name => name,
+        };
+        if self.bom && self.encoding == encoding_rs::UTF_8 {
+            format!("{} with BOM", name)
+        } else {
+            name.to_owned()
+        }
+    }
+
+    pub fn bom_len(&self) -> usize {
+        match self.bom {
+            false => 0,
+            true if self.encoding == encoding_rs::UTF_8 => 3,
+            true => 2,
+        }
+    }
+
+    // Bytes `text` takes up once encoded, not counting the BOM
+    pub fn encoded_len(&self, text: &str) -> usize {
+        if self.encoding == encoding_rs::UTF_8 {
+            text.len()
+        } else if self.encoding == encoding_rs::UTF_16LE || self.encoding == encoding_rs::UTF_16BE {
+            text.encode_utf16().count() * 2
+        } else {
+            self.encoding.encode(text).0.len()
+        }
+    }
+
+    // Fails instead of silently writing replacement characters
+    pub fn encode(&self, text: &str) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        if self.encoding == encoding_rs::UTF_16LE || self.encoding == encoding_rs::UTF_16BE {
+            let little_endian = self.encoding == encoding_rs::UTF_16LE;
+            if self.bom {
+                encode_utf16(&mut bytes, "\u{feff}", little_endian);
+            }
+            encode_utf16(&mut bytes, text, little_endian);
+            return Ok(bytes);
+        }
+
+        if self.bom && self.encoding == encoding_rs::UTF_8 {
+            bytes.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        let (encoded, _, had_errors) = self.encoding.encode(text);
+        if had_errors {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the text contains characters that can't be saved as {}", self.label()),
+            ));
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+// encoding_rs only decodes UTF-16, so encoding it is done by hand
+fn encode_utf16(bytes: &mut Vec<u8>, text: &str, little_endian: bool) {
+    for unit in text.encode_utf16() {
+        let pair = if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() };
+        bytes.extend_from_slice(&pair);
+    }
+}
+
+// BOM sniffing first, then BOM-less UTF-16, then valid UTF-8, then a statistical guess
+pub fn detect(bytes: &[u8]) -> TextEncoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return TextEncoding::new(encoding, true);
+    }
+    // NUL bytes are valid UTF-8, so this has to come first
+    if let Some(encoding) = guess_utf16(bytes) {
+        return TextEncoding::new(encoding, false);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return TextEncoding::default();
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    TextEncoding::new(detector.guess(None, true), false)
+}
+
+// Mostly-ASCII UTF-16 text has a zero byte in every other position
+fn guess_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let sample = &bytes[..bytes.len().min(4096)];
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd_zeros * 10 >= pairs * 7 && even_zeros * 10 < pairs {
+        Some(encoding_rs::UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 7 && odd_zeros * 10 < pairs {
+        Some(encoding_rs::UTF_16BE)
+    } else {
+        None
+    }
+}
+
+// The flag is set when some bytes weren't valid in the encoding and became U+FFFD
+pub fn decode(bytes: &[u8]) -> (String, TextEncoding, bool) {
+    let detected = detect(bytes);
+    let (text, _, malformed) = decode_with(bytes, detected.encoding);
+    (text, detected, malformed)
+}
+
+// Decodes with the given encoding, stripping (and remembering) a BOM that matches it
+pub fn decode_with(bytes: &[u8], encoding: &'static Encoding) -> (String, TextEncoding, bool) {
+    let (body, bom) = match Encoding::for_bom(bytes) {
+        Some((bom_encoding, bom_len)) if bom_encoding == encoding => (&bytes[bom_len..], true),
+        _ => (bytes, false),
+    };
+    let (text, malformed) = encoding.decode_without_bom_handling(body);
+    (text.into_owned(), TextEncoding::new(encoding, bom), malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        encode_utf16(&mut bytes, text, little_endian);
+        bytes
+    }
+
+    #[test]
+    fn boms() {
+        let detected = detect(b"\xEF\xBB\xBFhello");
+        assert!(detected == TextEncoding::new(encoding_rs::UTF_8, true));
+        assert!(detect(b"\xFF\xFEh\0i\0") == TextEncoding::new(encoding_rs::UTF_16LE, true));
+        assert!(detect(b"\xFE\xFF\0h\0i") == TextEncoding::new(encoding_rs::UTF_16BE, true));
+
+        // The BOM is stripped from the text and kept for saving
+        let (text, encoding, malformed) = decode(b"\xEF\xBB\xBFhello");
+        assert_eq!(text, "hello");
+        assert!(encoding.bom && !malformed);
+        assert_eq!(encoding.encode(&text).unwrap(), b"\xEF\xBB\xBFhello");
+    }
+
+    #[test]
+    fn utf16_without_bom() {
+        let le = utf16("plain text\n", true);
+        let be = utf16("plain text\n", false);
+        assert!(detect(&le) == TextEncoding::new(encoding_rs::UTF_16LE, false));
+        assert!(detect(&be) == TextEncoding::new(encoding_rs::UTF_16BE, false));
+        assert_eq!(decode(&le).0, "plain text\n");
+        // Odd lengths can't be UTF-16
+        assert!(guess_utf16(b"a\0b").is_none());
+    }
+
+    #[test]
+    fn utf8() {
+        assert!(detect("naïve café 日本".as_bytes()) == TextEncoding::default());
+        assert!(detect(b"") == TextEncoding::default());
+    }
+
+    #[test]
+    fn legacy_encodings() {
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode("Le café est très naïf, déjà vu à Noël.");
+        assert_eq!(detect(&latin1).encoding, encoding_rs::WINDOWS_1252);
+
+        let sample = "日本語のテキストファイルです。文字コードを判定します。";
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode(sample);
+        assert_eq!(detect(&sjis).encoding, encoding_rs::SHIFT_JIS);
+        let (text, _, malformed) = decode(&sjis);
+        assert_eq!(text, sample);
+        assert!(!malformed);
+    }
+
+    #[test]
+    fn wrong_encoding_is_flagged() {
+        let (_, _, malformed) = decode_with(&[0x82, 0xA0, 0xFF], encoding_rs::UTF_8);
+        assert!(malformed);
+        let (_, _, malformed) = decode_with("café".as_bytes(), encoding_rs::UTF_8);
+        assert!(!malformed);
+    }
+
+    #[test]
+    fn encode_refuses_unmappable_characters() {
+        let latin1 = TextEncoding::new(encoding_rs::WINDOWS_1252, false);
+        assert_eq!(latin1.encode("café").unwrap(), b"caf\xE9");
+        assert_eq!(latin1.encode("日本").unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // UTF-16 can hold anything, and writes its BOM in its own byte order
+        let utf16le = TextEncoding::new(encoding_rs::UTF_16LE, true);
+        assert_eq!(utf16le.encode("日").unwrap(), b"\xFF\xFE\xE5\x65");
+        assert_eq!(utf16le.encoded_len("日"), 2);
+    }
+}
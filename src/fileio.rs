@@ -0,0 +1,195 @@
+// src/fileio.rs
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// What to keep of the previous version of a file when it is overwritten
+#[derive(Clone, Copy, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum BackupMode {
+    #[default]
+    None,
+    // notes.txt~
+    Tilde,
+    // notes.txt.1.bak, notes.txt.2.bak, ...
+    Numbered,
+}
+
+// Writes to a temporary file next to the target and renames it into place,
+// so a crash or full disk mid-write never leaves a truncated file behind
+pub fn write_atomic(path: &Path, contents: &[u8], backup: BackupMode) -> io::Result<()> {
+    // Write through symlinks instead of replacing them with a regular file
+    let path = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path)?,
+        _ => path.to_path_buf(),
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let tmp_path = dir.join(format!(".{}.rpad-{}.tmp", file_name.to_string_lossy(), std::process::id()));
+    let original = fs::metadata(&path).ok();
+    // The rename would replace a read-only file as long as the directory is writable
+    if original.as_ref().is_some_and(|meta| meta.permissions().readonly()) {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "the file is read-only"));
+    }
+
+    let written = write_synced(&tmp_path, contents, original.as_ref());
+    let result = written
+        .and_then(|_| match original {
+            Some(_) => make_backup(&path, backup),
+            None => Ok(()),
+        })
+        .and_then(|_| fs::rename(&tmp_path, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result?;
+
+    sync_dir(&dir);
+    Ok(())
+}
+
+fn write_synced(tmp_path: &Path, contents: &[u8], original: Option<&fs::Metadata>) -> io::Result<()> {
+    // A leftover from an earlier failed save would make create_new fail
+    let _ = fs::remove_file(tmp_path);
+    let mut file = OpenOptions::new().write(true).create_new(true).open(tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    if let Some(meta) = original {
+        preserve_metadata(tmp_path, meta);
+    }
+    Ok(())
+}
+
+// Best effort: not being able to keep the owner (e.g. as a regular user) isn't an error
+fn preserve_metadata(tmp_path: &Path, original: &fs::Metadata) {
+    let _ = fs::set_permissions(tmp_path, original.permissions());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let _ = std::os::unix::fs::chown(tmp_path, Some(original.uid()), Some(original.gid()));
+    }
+}
+
+fn make_backup(path: &Path, backup: BackupMode) -> io::Result<()> {
+    let backup_path = match backup {
+        BackupMode::None => return Ok(()),
+        BackupMode::Tilde => append_to_name(path, "~"),
+        BackupMode::Numbered => (1..=u32::MAX)
+            .map(|n| append_to_name(path, &format!(".{}.bak", n)))
+            .find(|p| !p.exists())
+            .ok_or_else(|| io::Error::other("ran out of backup numbers"))?,
+    };
+    fs::copy(path, backup_path).map(|_| ())
+}
+
+fn append_to_name(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// Makes the rename itself durable
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(dir) = fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory per test, removed again when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rpad-fileio-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn replaces_the_file() {
+        let dir = TempDir::new("replace");
+        let path = dir.0.join("notes.txt");
+        write_atomic(&path, b"one", BackupMode::None).unwrap();
+        write_atomic(&path, b"two", BackupMode::None).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"two");
+        // Only the file itself is left; no temporary or backup files
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_through_symlinks() {
+        let dir = TempDir::new("symlink");
+        let target = dir.0.join("target.txt");
+        let link = dir.0.join("link.txt");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        write_atomic(&link, b"new", BackupMode::None).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("permissions");
+        let path = dir.0.join("script.sh");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+        write_atomic(&path, b"new", BackupMode::None).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o750);
+    }
+
+    #[test]
+    fn refuses_read_only_files() {
+        let dir = TempDir::new("readonly");
+        let path = dir.0.join("locked.txt");
+        fs::write(&path, "old").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        let error = write_atomic(&path, b"new", BackupMode::None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn backups() {
+        let dir = TempDir::new("backups");
+        let path = dir.0.join("notes.txt");
+        // Nothing to back up the first time
+        write_atomic(&path, b"1", BackupMode::Tilde).unwrap();
+        assert!(!dir.0.join("notes.txt~").exists());
+        write_atomic(&path, b"2", BackupMode::Tilde).unwrap();
+        assert_eq!(fs::read(dir.0.join("notes.txt~")).unwrap(), b"1");
+
+        write_atomic(&path, b"3", BackupMode::Numbered).unwrap();
+        write_atomic(&path, b"4", BackupMode::Numbered).unwrap();
+        assert_eq!(fs::read(dir.0.join("notes.txt.1.bak")).unwrap(), b"2");
+        assert_eq!(fs::read(dir.0.join("notes.txt.2.bak")).unwrap(), b"3");
+        assert_eq!(fs::read(&path).unwrap(), b"4");
+    }
+}
@@ -0,0 +1,414 @@
+// src/find_in_files.rs
+use crate::encoding;
+use crate::line_ending;
+use crate::search::{self, PresetTarget, Query, SearchHistory, SearchOptions};
+use eframe::egui;
+use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+// Bigger files are skipped rather than read into memory
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+// Characters of a matching line shown in the results
+const PREVIEW_LENGTH: usize = 200;
+
+pub struct FileHits {
+    pub path: PathBuf,
+    pub hits: Vec<Hit>,
+}
+
+pub struct Hit {
+    // 1-based, in characters
+    pub line: usize,
+    pub column: usize,
+    // Length of the match in characters, to select it when opened
+    pub length: usize,
+    pub preview: String,
+}
+
+enum Message {
+    File(FileHits),
+    Searched(usize),
+    Done,
+}
+
+// What the user asked for from the results panel
+pub enum Action {
+    Search,
+    Open { path: PathBuf, line: usize, column: usize, length: usize },
+    Replace { query: Query, replacement: String },
+}
+
+// The Find in Files panel; the search itself runs on a worker thread and streams back results
+pub struct FindInFiles {
+    pub show: bool,
+    pub find_text: String,
+    pub replace_text: String,
+    pub folder: String,
+    pub include: String,
+    pub exclude: String,
+    pub options: SearchOptions,
+    pub respect_gitignore: bool,
+    pub results: Vec<FileHits>,
+    // The pattern and query the results came from; editing the fields afterwards doesn't change
+    // what Replace in Files acts on
+    searched_for: Option<(String, Query)>,
+    preset_name: String,
+    error: Option<String>,
+    searched: usize,
+    running: Option<(Receiver<Message>, Arc<AtomicBool>)>,
+    // The replacement text, taken when the confirmation opens
+    confirm_replace: Option<String>,
+}
+
+impl Default for FindInFiles {
+    fn default() -> Self {
+        Self {
+            show: false,
+            find_text: String::new(),
+            replace_text: String::new(),
+            folder: String::new(),
+            include: String::new(),
+            exclude: String::new(),
+            options: SearchOptions::default(),
+            respect_gitignore: true,
+            results: Vec::new(),
+            searched_for: None,
+            preset_name: String::new(),
+            error: None,
+            searched: 0,
+            running: None,
+            confirm_replace: None,
+        }
+    }
+}
+
+impl FindInFiles {
+    pub fn query(&self) -> Result<Option<Query>, String> {
+        Query::new(&self.find_text, self.options)
+    }
+
+    // Absolute, so results match the paths of open documents
+    fn folder(&self) -> PathBuf {
+        let folder = PathBuf::from(self.folder.trim());
+        std::path::absolute(&folder).unwrap_or(folder)
+    }
+
+    // Starts a new search, cancelling any that is still running. `open` holds the text of open
+    // documents, which is searched instead of what is on disk.
+    pub fn start(&mut self, ctx: &egui::Context, open: HashMap<PathBuf, String>) {
+        self.clear_results();
+        self.error = None;
+
+        let query = match self.query() {
+            Ok(Some(query)) => query,
+            Ok(None) => return,
+            Err(message) => {
+                self.error = Some(message);
+                return;
+            }
+        };
+        let folder = self.folder();
+        if !folder.is_dir() {
+            self.error = Some(format!("{} is not a folder", folder.display()));
+            return;
+        }
+        self.searched_for = Some((self.find_text.clone(), query.clone()));
+        let mut walker = WalkBuilder::new(&folder);
+        // Hidden files and folders such as .git are always skipped; the checkbox is only about
+        // ignore files. Notes folders often have a .gitignore without being a repository.
+        let ignore_files = self.respect_gitignore;
+        walker
+            .hidden(true)
+            .parents(ignore_files)
+            .ignore(ignore_files)
+            .git_ignore(ignore_files)
+            .git_global(ignore_files)
+            .git_exclude(ignore_files)
+            .require_git(false);
+        match globs(&folder, &self.include, &self.exclude) {
+            Ok(overrides) => {
+                walker.overrides(overrides);
+            }
+            Err(message) => {
+                self.error = Some(message);
+                return;
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.running = Some((receiver, cancel.clone()));
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            search(walker, &query, &open, &sender, &cancel, &ctx);
+            let _ = sender.send(Message::Done);
+            ctx.request_repaint();
+        });
+    }
+
+    pub fn stop(&mut self) {
+        if let Some((_, cancel)) = self.running.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Moves results the worker has found so far into the panel
+    fn poll(&mut self) {
+        let Some((receiver, _)) = &self.running else {
+            return;
+        };
+        while let Ok(message) = receiver.try_recv() {
+            match message {
+                Message::File(file) => self.results.push(file),
+                Message::Searched(count) => self.searched = count,
+                Message::Done => {
+                    self.running = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn clear_results(&mut self) {
+        self.stop();
+        self.results.clear();
+        self.searched_for = None;
+        self.confirm_replace = None;
+        self.searched = 0;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, history: &mut SearchHistory) -> Option<Action> {
+        self.poll();
+        let mut action = None;
+        let mut open = self.show;
+        egui::Window::new("Find in Files")
+            .open(&mut open)
+            .default_size([560.0, 480.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("find_in_files_fields").num_columns(2).show(ui, |ui| {
+                    ui.label("Find:");
+                    let response = ui.horizontal(|ui| search::history_field(ui, &mut self.find_text, &history.find)).inner;
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        action = Some(Action::Search);
+                    }
+                    ui.end_row();
+                    ui.label("Replace:");
+                    ui.horizontal(|ui| search::history_field(ui, &mut self.replace_text, &history.replace));
+                    ui.end_row();
+                    ui.label("Folder:");
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.folder);
+                        if ui.button("Browse...").clicked()
+                            && let Some(folder) = rfd::FileDialog::new().pick_folder()
+                        {
+                            self.folder = folder.display().to_string();
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Include:");
+                    ui.text_edit_singleline(&mut self.include)
+                        .on_hover_text("Globs separated by commas, e.g. *.md, notes/**");
+                    ui.end_row();
+                    ui.label("Exclude:");
+                    ui.text_edit_singleline(&mut self.exclude)
+                        .on_hover_text("Globs separated by commas, e.g. *.log, target/");
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.options.match_case, "Match Case");
+                    ui.checkbox(&mut self.options.whole_word, "Whole Word");
+                    ui.checkbox(&mut self.options.regex, "Regular Expression");
+                    ui.checkbox(&mut self.respect_gitignore, "Respect .gitignore");
+                    search::presets_menu(
+                        ui,
+                        history,
+                        &mut self.preset_name,
+                        PresetTarget {
+                            find: &mut self.find_text,
+                            replace: &mut self.replace_text,
+                            options: &mut self.options,
+                        },
+                    );
+                });
+                if let Err(message) = self.query() {
+                    ui.colored_label(ui.visuals().error_fg_color, message);
+                }
+                ui.horizontal(|ui| {
+                    if self.running.is_some() {
+                        if ui.button("Stop").clicked() {
+                            self.stop();
+                        }
+                        ui.spinner();
+                    } else if ui.button("Search").clicked() {
+                        action = Some(Action::Search);
+                    }
+                    let can_replace = self.running.is_none() && !self.results.is_empty() && self.searched_for.is_some();
+                    if ui.add_enabled(can_replace, egui::Button::new("Replace in Files...")).clicked() {
+                        self.confirm_replace = Some(self.replace_text.clone());
+                    }
+                    ui.label(self.summary());
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.separator();
+                egui::ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+                    let folder = self.folder();
+                    for file in &self.results {
+                        let name = file.path.strip_prefix(&folder).unwrap_or(&file.path);
+                        egui::CollapsingHeader::new(format!("{} ({})", name.display(), file.hits.len()))
+                            .id_source(&file.path)
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for hit in &file.hits {
+                                    let text = egui::RichText::new(format!("{:>5}: {}", hit.line, hit.preview)).monospace();
+                                    if ui.selectable_label(false, text).clicked() {
+                                        action = Some(Action::Open {
+                                            path: file.path.clone(),
+                                            line: hit.line,
+                                            column: hit.column,
+                                            length: hit.length,
+                                        });
+                                    }
+                                }
+                            });
+                    }
+                });
+            });
+        self.show = open;
+        if !open {
+            self.stop();
+        }
+
+        if let Some(replacement) = &self.confirm_replace
+            && let Some((pattern, _)) = &self.searched_for
+        {
+            let files = self.results.len();
+            let hits: usize = self.results.iter().map(|f| f.hits.len()).sum();
+            let (mut confirmed, mut cancelled) = (false, false);
+            egui::Window::new("Replace in Files")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "Replace {} matches of \"{}\" in {} files with \"{}\"?",
+                        hits, pattern, files, replacement
+                    ));
+                    ui.label("Files that are not open are saved straight away and can't be undone.");
+                    ui.horizontal(|ui| {
+                        confirmed = ui.button("Replace").clicked();
+                        cancelled = ui.button("Cancel").clicked();
+                    });
+                });
+            if confirmed
+                && let Some(replacement) = self.confirm_replace.take()
+                && let Some((_, query)) = self.searched_for.take()
+            {
+                action = Some(Action::Replace { query, replacement });
+            } else if cancelled {
+                self.confirm_replace = None;
+            }
+        }
+        action
+    }
+
+    fn summary(&self) -> String {
+        let hits: usize = self.results.iter().map(|f| f.hits.len()).sum();
+        if self.running.is_some() {
+            format!("Searching... {} matches in {} of {} files", hits, self.results.len(), self.searched)
+        } else if self.searched > 0 {
+            format!("{} matches in {} files ({} searched)", hits, self.results.len(), self.searched)
+        } else {
+            String::new()
+        }
+    }
+}
+
+fn globs(folder: &Path, include: &str, exclude: &str) -> Result<ignore::overrides::Override, String> {
+    let split = |text: &str| {
+        text.split(',')
+            .map(str::trim)
+            .filter(|glob| !glob.is_empty())
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    let mut builder = OverrideBuilder::new(folder);
+    for glob in split(include) {
+        builder.add(&glob).map_err(|e| e.to_string())?;
+    }
+    // Overrides are whitelists unless negated
+    for glob in split(exclude) {
+        builder.add(&format!("!{}", glob)).map_err(|e| e.to_string())?;
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn search(
+    walker: WalkBuilder,
+    query: &Query,
+    open: &HashMap<PathBuf, String>,
+    sender: &Sender<Message>,
+    cancel: &AtomicBool,
+    ctx: &egui::Context,
+) {
+    let mut searched = 0;
+    for entry in walker.build().flatten() {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.into_path();
+        let Some(text) = open.get(&path).cloned().or_else(|| read_text(&path)) else {
+            continue;
+        };
+        searched += 1;
+        let hits = find_hits(query, &text);
+        if !hits.is_empty() {
+            let _ = sender.send(Message::File(FileHits { path, hits }));
+        }
+        let _ = sender.send(Message::Searched(searched));
+        ctx.request_repaint();
+    }
+}
+
+// The file as it would appear in the editor, or None for binary and oversized files
+pub fn read_text(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    let (text, _, _) = encoding::decode(&bytes);
+    if text.contains('\0') {
+        return None;
+    }
+    Some(line_ending::normalize(&text).into_owned())
+}
+
+fn find_hits(query: &Query, text: &str) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    let mut line = 1;
+    let mut line_start = 0;
+    for range in query.find_all(text, None) {
+        line += text[line_start..range.start].matches('\n').count();
+        line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[range.start..].find('\n').map_or(text.len(), |i| range.start + i);
+        let preview: String = text[line_start..line_end].trim_end().chars().take(PREVIEW_LENGTH).collect();
+        hits.push(Hit {
+            line,
+            column: text[line_start..range.start].chars().count() + 1,
+            length: text[range].chars().count(),
+            preview: preview.replace('\t', "    "),
+        });
+    }
+    hits
+}
@@ -0,0 +1,156 @@
+// src/fonts.rs
+use eframe::egui;
+use egui::{FontData, FontDefinitions, FontFamily};
+use std::sync::mpsc::{self, Receiver};
+
+// Space Mono, the editor font unless another is picked
+pub const BUNDLED: &str = "Space Mono";
+static BUNDLED_DATA: &[u8] = include_bytes!("../assets/fonts/default.ttf");
+
+// Tried in order for characters the chosen fonts don't have; the first installed one of each
+// group is used. Color-only emoji fonts can't be drawn, and egui's own emoji font covers those.
+const CJK_FALLBACKS: &[&str] = &[
+    "Noto Sans CJK SC",
+    "Noto Sans CJK JP",
+    "Noto Sans SC",
+    "Source Han Sans SC",
+    "Source Han Sans",
+    "WenQuanYi Micro Hei",
+    "Droid Sans Fallback",
+    "Microsoft YaHei",
+    "Yu Gothic",
+    "Malgun Gothic",
+    "PingFang SC",
+    "Hiragino Sans",
+];
+const EMOJI_FALLBACKS: &[&str] = &["Segoe UI Emoji", "Noto Emoji", "Symbola", "Apple Symbols"];
+
+// Fonts installed on the system, found via fontconfig's directories on Linux
+pub struct SystemFonts {
+    db: fontdb::Database,
+    // Family names, sorted
+    pub all: Vec<String>,
+    pub monospace: Vec<String>,
+}
+
+impl SystemFonts {
+    fn load() -> Self {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        let mut all = Vec::new();
+        let mut monospace = Vec::new();
+        for face in db.faces() {
+            let Some((family, _)) = face.families.first() else {
+                continue;
+            };
+            all.push(family.clone());
+            if face.monospaced {
+                monospace.push(family.clone());
+            }
+        }
+        for names in [&mut all, &mut monospace] {
+            names.sort_by_key(|name| name.to_lowercase());
+            names.dedup();
+        }
+        Self { db, all, monospace }
+    }
+
+    // The regular face of a family
+    fn face_data(&self, family: &str) -> Option<FontData> {
+        let id = self.db.query(&fontdb::Query {
+            families: &[fontdb::Family::Name(family)],
+            ..Default::default()
+        })?;
+        self.db.with_face_data(id, |data, index| {
+            let mut font = FontData::from_owned(data.to_vec());
+            font.index = index;
+            font
+        })
+    }
+}
+
+// Puts the chosen fonts into egui, and keeps them there as the choice changes
+#[derive(Default)]
+pub struct Fonts {
+    system: Option<SystemFonts>,
+    // Scanning the system's fonts can take a while, so it happens off the UI thread
+    loading: Option<Receiver<SystemFonts>>,
+    // The editor and interface fonts last installed, and whether system fonts were available then
+    applied: Option<(Option<String>, Option<String>, bool)>,
+}
+
+impl Fonts {
+    pub fn start_loading(&mut self, ctx: &egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(SystemFonts::load());
+            ctx.request_repaint();
+        });
+        self.loading = Some(receiver);
+    }
+
+    // None until the scan has finished
+    pub fn system(&self) -> Option<&SystemFonts> {
+        self.system.as_ref()
+    }
+
+    // Installs the fonts when the choice changes or the system fonts become available.
+    // Returns the chosen fonts that aren't installed.
+    pub fn update(&mut self, ctx: &egui::Context, editor: Option<&str>, interface: Option<&str>) -> Vec<String> {
+        if let Some(receiver) = &self.loading
+            && let Ok(system) = receiver.try_recv()
+        {
+            self.system = Some(system);
+            self.loading = None;
+        }
+        let wanted = (editor.map(str::to_owned), interface.map(str::to_owned), self.system.is_some());
+        if self.applied.as_ref() == Some(&wanted) {
+            return Vec::new();
+        }
+        let (definitions, missing) = self.definitions(editor, interface);
+        ctx.set_fonts(definitions);
+        self.applied = Some(wanted);
+        missing
+    }
+
+    fn definitions(&self, editor: Option<&str>, interface: Option<&str>) -> (FontDefinitions, Vec<String>) {
+        let mut definitions = FontDefinitions::default();
+        let mut missing = Vec::new();
+        definitions.font_data.insert(BUNDLED.to_owned(), FontData::from_static(BUNDLED_DATA));
+        definitions
+            .families
+            .entry(FontFamily::Monospace)
+            .or_default()
+            .insert(0, BUNDLED.to_owned());
+
+        let Some(system) = &self.system else {
+            return (definitions, missing);
+        };
+        for (family, name) in [(FontFamily::Monospace, editor), (FontFamily::Proportional, interface)] {
+            let Some(name) = name else {
+                continue;
+            };
+            if name != BUNDLED {
+                let Some(data) = system.face_data(name) else {
+                    missing.push(name.to_owned());
+                    continue;
+                };
+                definitions.font_data.insert(name.to_owned(), data);
+            }
+            let names = definitions.families.entry(family).or_default();
+            names.retain(|n| n != name);
+            names.insert(0, name.to_owned());
+        }
+        for fallbacks in [CJK_FALLBACKS, EMOJI_FALLBACKS] {
+            let Some((name, data)) = fallbacks.iter().find_map(|&name| Some((name, system.face_data(name)?))) else {
+                continue;
+            };
+            definitions.font_data.insert(name.to_owned(), data);
+            for family in [FontFamily::Monospace, FontFamily::Proportional] {
+                definitions.families.entry(family).or_default().push(name.to_owned());
+            }
+        }
+        (definitions, missing)
+    }
+}
@@ -0,0 +1,98 @@
+// src/goto.rs
+use std::num::IntErrorKind;
+
+// A position typed into the Go To dialog
+#[derive(Clone, Copy)]
+pub enum Target {
+    // 1-based
+    Line { line: usize, column: Option<usize> },
+    // Lines up or down from the cursor
+    Relative(isize),
+    // Bytes into the file as written to disk, as quoted by tools that report offsets
+    Offset(usize),
+}
+
+pub const HINT: &str = "line, line:column, +N, -N or #offset";
+
+pub fn parse(text: &str) -> Result<Target, String> {
+    let text = text.trim();
+    let number = |s: &str| {
+        let s = s.trim();
+        s.parse::<usize>().map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow => format!("{} is too large", s),
+            _ => format!("Expected {}", HINT),
+        })
+    };
+    let lines = |s: &str| isize::try_from(number(s)?).map_err(|_| format!("{} is too large", s.trim()));
+    if let Some(offset) = text.strip_prefix('#') {
+        return Ok(Target::Offset(number(offset)?));
+    }
+    if let Some(count) = text.strip_prefix('+') {
+        return Ok(Target::Relative(lines(count)?));
+    }
+    if let Some(count) = text.strip_prefix('-') {
+        return Ok(Target::Relative(-lines(count)?));
+    }
+    let (line, column) = match text.split_once([':', ',']) {
+        Some((line, column)) => (number(line)?, Some(number(column)?)),
+        None => (number(text)?, None),
+    };
+    if line == 0 || column == Some(0) {
+        return Err("Lines and columns start at 1".to_owned());
+    }
+    Ok(Target::Line { line, column })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_and_columns() {
+        assert!(matches!(parse("42"), Ok(Target::Line { line: 42, column: None })));
+        assert!(matches!(parse(" 42:7 "), Ok(Target::Line { line: 42, column: Some(7) })));
+        assert!(matches!(parse("42,7"), Ok(Target::Line { line: 42, column: Some(7) })));
+        assert!(matches!(parse("42 : 7"), Ok(Target::Line { line: 42, column: Some(7) })));
+        assert!(parse("0").is_err());
+        assert!(parse("3:0").is_err());
+    }
+
+    #[test]
+    fn relative() {
+        assert!(matches!(parse("+5"), Ok(Target::Relative(5))));
+        assert!(matches!(parse("-5"), Ok(Target::Relative(-5))));
+        assert!(matches!(parse("+0"), Ok(Target::Relative(0))));
+        assert!(parse("+").is_err());
+        assert!(parse("+-5").is_err());
+        assert!(parse("--5").is_err());
+    }
+
+    #[test]
+    fn offsets() {
+        assert!(matches!(parse("#0"), Ok(Target::Offset(0))));
+        assert!(matches!(parse("#1024"), Ok(Target::Offset(1024))));
+        assert!(parse("#").is_err());
+        assert!(parse("#-1").is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        for text in ["", "abc", "%50", "1.5", "3:", ":3", "1:2:3", "#1:2"] {
+            assert!(parse(text).is_err(), "{:?} should be rejected", text);
+        }
+    }
+
+    #[test]
+    fn too_large() {
+        let max = usize::MAX.to_string();
+        // Past usize
+        assert_eq!(parse("99999999999999999999").err().unwrap(), "99999999999999999999 is too large");
+        assert!(parse("+99999999999999999999").is_err());
+        // Fits usize but not isize, so it would have wrapped to a negative jump
+        assert!(parse(&format!("+{}", max)).is_err());
+        assert!(parse(&format!("-{}", max)).is_err());
+        assert!(matches!(parse(&format!("+{}", isize::MAX)), Ok(Target::Relative(isize::MAX))));
+        assert!(matches!(parse(&format!("-{}", isize::MAX)), Ok(Target::Relative(n)) if n == -isize::MAX));
+        assert!(matches!(parse(&max), Ok(Target::Line { line: usize::MAX, .. })));
+    }
+}
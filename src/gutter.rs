@@ -0,0 +1,96 @@
+// src/gutter.rs
+use crate::theme::Theme;
+use eframe::egui;
+use egui::text::Galley;
+use egui::{Align2, FontId, Pos2, Rect, Shape};
+
+// Space either side of the numbers
+const PADDING: f32 = 8.0;
+
+pub fn width(ui: &egui::Ui, font_id: &FontId, lines: usize) -> f32 {
+    let digits = lines.max(1).ilog10() as f32 + 1.0;
+    let digit_width = ui.fonts(|f| f.glyph_width(font_id, '0'));
+    digits.max(2.0) * digit_width + 2.0 * PADDING
+}
+
+// 0-based logical line (paragraph) of the row at screen height `y`
+pub fn line_at(galley: &Galley, text_pos: Pos2, y: f32) -> usize {
+    galley.cursor_from_pos(egui::vec2(0.0, y - text_pos.y)).pcursor.paragraph
+}
+
+// Screen-space vertical extent of each row that starts a logical line, with its 0-based line number.
+// With word wrap a line spans several rows; only its first is numbered.
+fn line_rows(galley: &Galley, text_pos: Pos2) -> impl Iterator<Item = (usize, f32, f32)> + '_ {
+    let mut line = 0;
+    let mut starts_line = true;
+    galley.rows.iter().filter_map(move |row| {
+        let numbered = starts_line.then_some(line);
+        if row.ends_with_newline {
+            line += 1;
+        }
+        starts_line = row.ends_with_newline;
+        numbered.map(|line| (line, text_pos.y + row.rect.top(), text_pos.y + row.rect.bottom()))
+    })
+}
+
+// Screen-space rows covered by logical line `line`
+fn line_span(galley: &Galley, text_pos: Pos2, line: usize) -> Option<(f32, f32)> {
+    let mut current = 0;
+    let mut span: Option<(f32, f32)> = None;
+    for row in &galley.rows {
+        if current == line {
+            let (top, bottom) = (text_pos.y + row.rect.top(), text_pos.y + row.rect.bottom());
+            span = Some(span.map_or((top, bottom), |(t, _)| (t, bottom)));
+        }
+        if row.ends_with_newline {
+            current += 1;
+            if current > line {
+                break;
+            }
+        }
+    }
+    span
+}
+
+// The current line's band across the text area, to paint behind the text
+pub fn current_line_shape(theme: &Theme, galley: &Galley, text_pos: Pos2, line: usize, area: Rect) -> Shape {
+    match line_span(galley, text_pos, line) {
+        Some((top, bottom)) => Shape::rect_filled(
+            Rect::from_x_y_ranges(area.x_range(), top..=bottom),
+            0.0,
+            theme.current_line.0,
+        ),
+        None => Shape::Noop,
+    }
+}
+
+pub fn paint(
+    ui: &egui::Ui,
+    theme: &Theme,
+    rect: Rect,
+    galley: &Galley,
+    text_pos: Pos2,
+    current_line: usize,
+    font_id: &FontId,
+) {
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, theme.gutter_background.0);
+    if let Some((top, bottom)) = line_span(galley, text_pos, current_line) {
+        painter.rect_filled(Rect::from_x_y_ranges(rect.x_range(), top..=bottom), 0.0, theme.current_line.0);
+    }
+    for (line, top, bottom) in line_rows(galley, text_pos) {
+        if bottom < rect.top() {
+            continue;
+        }
+        if top > rect.bottom() {
+            break;
+        }
+        let color = if line == current_line {
+            theme.gutter_current_text.0
+        } else {
+            theme.gutter_text.0
+        };
+        let pos = egui::pos2(rect.right() - PADDING, top);
+        painter.text(pos, Align2::RIGHT_TOP, (line + 1).to_string(), font_id.clone(), color);
+    }
+}
@@ -0,0 +1,283 @@
+// src/highlight.rs
+use crate::theme::{Color, Theme};
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use syntect::highlighting::{
+    self, FontStyle, HighlightState, Highlighter, RangedHighlightIterator, ScopeSelectors, StyleModifier, ThemeItem,
+    ThemeSettings,
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+
+// Time spent highlighting per frame; the rest of a big file is done over the following frames
+const FRAME_BUDGET: Duration = Duration::from_millis(12);
+
+pub const PLAIN_TEXT: &str = "Plain Text";
+
+// Bundled with syntect, so highlighting works offline. Loaded on first use.
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+static NEXT_THEME_ID: AtomicU64 = AtomicU64::new(0);
+
+// A theme's token colors as syntect scope rules
+pub struct SyntaxTheme {
+    // Tells highlighting caches made with another theme apart
+    id: u64,
+    theme: highlighting::Theme,
+}
+
+impl SyntaxTheme {
+    pub fn new(theme: &Theme) -> Self {
+        let color = |color: Color| {
+            let [r, g, b, a] = color.0.to_srgba_unmultiplied();
+            highlighting::Color { r, g, b, a }
+        };
+        let colors = &theme.syntax;
+        // More specific selectors win, e.g. keyword.operator over keyword
+        let rules = [
+            ("comment", colors.comment),
+            ("string, constant.character", colors.string),
+            ("constant.numeric", colors.number),
+            ("constant.language, constant.other, support.constant, variable.other.constant", colors.constant),
+            ("keyword, storage", colors.keyword),
+            ("keyword.operator, punctuation.separator, punctuation.accessor", colors.operator),
+            ("entity.name.function, support.function, variable.function", colors.function),
+            ("entity.name.type, entity.name.class, entity.other.inherited-class, storage.type, support.type, support.class", colors.type_name),
+        ];
+        let scopes = rules
+            .into_iter()
+            .filter_map(|(selector, foreground)| {
+                Some(ThemeItem {
+                    scope: ScopeSelectors::from_str(selector).ok()?,
+                    style: StyleModifier {
+                        foreground: Some(color(foreground)),
+                        ..Default::default()
+                    },
+                })
+            })
+            .collect();
+        Self {
+            id: NEXT_THEME_ID.fetch_add(1, Ordering::Relaxed),
+            theme: highlighting::Theme {
+                name: Some(theme.name.clone()),
+                settings: ThemeSettings {
+                    foreground: Some(color(theme.text)),
+                    background: Some(color(theme.background)),
+                    ..Default::default()
+                },
+                scopes,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+// Languages for the picker, by name
+pub fn languages() -> Vec<&'static str> {
+    let mut names: Vec<_> = syntaxes()
+        .syntaxes()
+        .iter()
+        .filter(|s| !s.hidden)
+        .map(|s| s.name.as_str())
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+// From the file name or extension, then the first line (e.g. a shebang)
+fn detect(path: Option<&Path>, text: &str) -> &'static str {
+    let set = syntaxes();
+    let by_path = path.and_then(|path| {
+        let name = path.file_name()?.to_str()?;
+        set.find_syntax_by_extension(name)
+            .or_else(|| set.find_syntax_by_extension(path.extension()?.to_str()?))
+    });
+    let first_line = text.lines().next().unwrap_or_default();
+    by_path
+        .or_else(|| set.find_syntax_by_first_line(first_line))
+        .map_or(PLAIN_TEXT, |syntax| syntax.name.as_str())
+}
+
+struct Line {
+    text: String,
+    // Parser state at the end of the line, where the next line carries on from
+    after: (ParseState, HighlightState),
+    // Byte length, color and italics of each styled run
+    spans: Vec<(usize, egui::Color32, bool)>,
+}
+
+// A document's highlighted lines, kept between frames so an edit only re-highlights from the
+// changed line until the parser state matches what it was before
+#[derive(Default)]
+pub struct Highlighting {
+    // The language picked by hand; None to detect it
+    pub language: Option<String>,
+    detected: Option<(Option<PathBuf>, &'static str)>,
+    // Language and theme the cached lines were highlighted with
+    key: Option<(&'static str, u64)>,
+    lines: Vec<Line>,
+    complete: bool,
+}
+
+impl Highlighting {
+    pub fn language(&mut self, path: Option<&Path>, text: &str) -> &'static str {
+        if let Some(name) = &self.language
+            && let Some(syntax) = syntaxes().find_syntax_by_name(name)
+        {
+            return syntax.name.as_str();
+        }
+        match &self.detected {
+            Some((detected_for, name)) if detected_for.as_deref() == path => name,
+            _ => {
+                let name = detect(path, text);
+                self.detected = Some((path.map(Path::to_path_buf), name));
+                name
+            }
+        }
+    }
+
+    // False while a large file is still being worked through; repaint to carry on
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    fn update(&mut self, text: &str, language: &'static str, theme: &SyntaxTheme) {
+        if self.key != Some((language, theme.id)) {
+            self.key = Some((language, theme.id));
+            self.lines.clear();
+        }
+        let set = syntaxes();
+        let syntax = set.find_syntax_by_name(language).unwrap_or_else(|| set.find_syntax_plain_text());
+        let highlighter = Highlighter::new(&theme.theme);
+        let initial = || (ParseState::new(syntax), HighlightState::new(&highlighter, ScopeStack::new()));
+
+        let new: Vec<&str> = text.split_inclusive('\n').collect();
+        let mut lines = std::mem::take(&mut self.lines);
+        let prefix = lines.iter().zip(&new).take_while(|(old, new)| old.text == **new).count();
+        let suffix = lines[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(old, new)| old.text == **new)
+            .count();
+        // Unchanged lines at the end, and the state they were highlighted from
+        let mut tail = lines.split_off(lines.len() - suffix);
+        let tail_state = lines.last().map_or_else(initial, |line| line.after.clone());
+        lines.truncate(prefix);
+
+        let tail_start = new.len() - suffix;
+        let (mut parse_state, mut highlight_state) = lines.last().map_or_else(initial, |line| line.after.clone());
+        let started = Instant::now();
+        self.complete = true;
+        for (index, text) in new.iter().enumerate().skip(prefix) {
+            if index >= tail_start {
+                let k = index - tail_start;
+                let expected = if k == 0 { &tail_state } else { &tail[k - 1].after };
+                if expected.0 == parse_state && expected.1 == highlight_state {
+                    lines.extend(tail.drain(k..));
+                    break;
+                }
+            }
+            if started.elapsed() > FRAME_BUDGET {
+                self.complete = false;
+                break;
+            }
+            let ops = parse_state.parse_line(text, set).unwrap_or_default();
+            let spans = RangedHighlightIterator::new(&mut highlight_state, &ops, text, &highlighter)
+                .map(|(style, _, range)| {
+                    let c = style.foreground;
+                    let color = egui::Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a);
+                    (range.len(), color, style.font_style.contains(FontStyle::ITALIC))
+                })
+                .collect();
+            lines.push(Line {
+                text: (*text).to_owned(),
+                after: (parse_state.clone(), highlight_state.clone()),
+                spans,
+            });
+        }
+        self.lines = lines;
+    }
+
+    // Lays out `text` in its language's colors, with search matches given a background.
+    // Whatever hasn't been highlighted yet is shown in the plain text color.
+    pub fn layout_job(
+        &mut self,
+        text: &str,
+        language: &'static str,
+        theme: &SyntaxTheme,
+        format: TextFormat,
+        matches: &[Range<usize>],
+        match_background: egui::Color32,
+    ) -> LayoutJob {
+        let mut job = JobBuilder {
+            job: LayoutJob::default(),
+            text,
+            matches,
+            next_match: 0,
+            match_background,
+        };
+        if language == PLAIN_TEXT {
+            self.lines.clear();
+            self.complete = true;
+            job.push(0..text.len(), &format);
+            return job.job;
+        }
+
+        self.update(text, language, theme);
+        let mut offset = 0;
+        for line in &self.lines {
+            for &(length, color, italics) in &line.spans {
+                let span = TextFormat {
+                    color,
+                    italics,
+                    ..format.clone()
+                };
+                job.push(offset..offset + length, &span);
+                offset += length;
+            }
+        }
+        job.push(offset..text.len(), &format);
+        job.job
+    }
+}
+
+struct JobBuilder<'a> {
+    job: LayoutJob,
+    text: &'a str,
+    // Sorted, not overlapping
+    matches: &'a [Range<usize>],
+    next_match: usize,
+    match_background: egui::Color32,
+}
+
+impl JobBuilder<'_> {
+    // Appends a run of text, split wherever a search match starts or ends
+    fn push(&mut self, range: Range<usize>, format: &TextFormat) {
+        let mut start = range.start;
+        while start < range.end {
+            while self.matches.get(self.next_match).is_some_and(|m| m.end <= start) {
+                self.next_match += 1;
+            }
+            let (end, in_match) = match self.matches.get(self.next_match) {
+                Some(m) if m.start <= start => (m.end.min(range.end), true),
+                Some(m) => (m.start.min(range.end), false),
+                None => (range.end, false),
+            };
+            let mut format = format.clone();
+            if in_match {
+                format.background = self.match_background;
+            }
+            self.job.append(&self.text[start..end], 0.0, format);
+            start = end;
+        }
+    }
+}
@@ -0,0 +1,59 @@
+// src/indent.rs
+
+// What the Tab key inserts in a document
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Indent {
+    #[default]
+    Tabs,
+    Spaces(usize),
+}
+
+impl Indent {
+    pub const ALL: [Indent; 4] = [Indent::Tabs, Indent::Spaces(2), Indent::Spaces(4), Indent::Spaces(8)];
+
+    pub fn label(&self) -> String {
+        match self {
+            Indent::Tabs => "Tabs".to_owned(),
+            Indent::Spaces(width) => format!("Spaces: {}", width),
+        }
+    }
+
+    // What Tab inserts at a given (0-based) column
+    pub fn text_at(&self, column: usize) -> String {
+        match self {
+            Indent::Tabs => "\t".to_owned(),
+            Indent::Spaces(width) => " ".repeat(width - column % width),
+        }
+    }
+}
+
+// Guesses from the leading whitespace of each line; None if nothing is indented
+pub fn detect(text: &str) -> Option<Indent> {
+    let (mut tabs, mut spaces) = (0, 0);
+    // How often each step in indentation between neighbouring lines occurs
+    let mut steps = [0usize; 9];
+    let mut previous = 0;
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        if line.starts_with('\t') {
+            tabs += 1;
+            continue;
+        }
+        let width = line.len() - line.trim_start_matches(' ').len();
+        if width > 0 {
+            spaces += 1;
+        }
+        let step = width.abs_diff(previous);
+        if step < steps.len() {
+            steps[step] += 1;
+        }
+        previous = width;
+    }
+    if tabs == 0 && spaces == 0 {
+        return None;
+    }
+    if tabs >= spaces {
+        return Some(Indent::Tabs);
+    }
+    let width = [2, 4, 8].into_iter().max_by_key(|&w| (steps[w], w == 4)).unwrap_or(4);
+    Some(Indent::Spaces(width))
+}
@@ -0,0 +1,661 @@
+// src/keymap.rs
+use eframe::egui;
+use egui::Key;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+// Everything that can be bound to a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    NewFile,
+    OpenFile,
+    Save,
+    SaveAs,
+    CloseTab,
+    ReopenClosedTab,
+    NextTab,
+    PreviousTab,
+    Exit,
+    Undo,
+    Redo,
+    UndoHistory,
+    FindReplace,
+    FindInFiles,
+    FindNext,
+    FindPrevious,
+    GoTo,
+    WordWrap,
+    Font,
+    StatusBar,
+    LineNumbers,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Theme,
+    KeyboardShortcuts,
+}
+
+impl Command {
+    pub const ALL: [Command; 26] = [
+        Command::NewFile,
+        Command::OpenFile,
+        Command::Save,
+        Command::SaveAs,
+        Command::CloseTab,
+        Command::ReopenClosedTab,
+        Command::NextTab,
+        Command::PreviousTab,
+        Command::Exit,
+        Command::Undo,
+        Command::Redo,
+        Command::UndoHistory,
+        Command::FindReplace,
+        Command::FindInFiles,
+        Command::FindNext,
+        Command::FindPrevious,
+        Command::GoTo,
+        Command::WordWrap,
+        Command::Font,
+        Command::StatusBar,
+        Command::LineNumbers,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::ZoomReset,
+        Command::Theme,
+        Command::KeyboardShortcuts,
+    ];
+
+    // Names commands in the keymap file, so they must not change
+    pub fn id(self) -> &'static str {
+        match self {
+            Command::NewFile => "file.new",
+            Command::OpenFile => "file.open",
+            Command::Save => "file.save",
+            Command::SaveAs => "file.save_as",
+            Command::CloseTab => "tab.close",
+            Command::ReopenClosedTab => "tab.reopen_closed",
+            Command::NextTab => "tab.next",
+            Command::PreviousTab => "tab.previous",
+            Command::Exit => "app.exit",
+            Command::Undo => "edit.undo",
+            Command::Redo => "edit.redo",
+            Command::UndoHistory => "edit.undo_history",
+            Command::FindReplace => "search.find_replace",
+            Command::FindInFiles => "search.find_in_files",
+            Command::FindNext => "search.find_next",
+            Command::FindPrevious => "search.find_previous",
+            Command::GoTo => "search.go_to",
+            Command::WordWrap => "format.word_wrap",
+            Command::Font => "format.font",
+            Command::StatusBar => "view.status_bar",
+            Command::LineNumbers => "view.line_numbers",
+            Command::ZoomIn => "view.zoom_in",
+            Command::ZoomOut => "view.zoom_out",
+            Command::ZoomReset => "view.zoom_reset",
+            Command::Theme => "view.theme",
+            Command::KeyboardShortcuts => "help.keyboard_shortcuts",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Command::NewFile => "New",
+            Command::OpenFile => "Open...",
+            Command::Save => "Save",
+            Command::SaveAs => "Save As...",
+            Command::CloseTab => "Close Tab",
+            Command::ReopenClosedTab => "Reopen Closed Tab",
+            Command::NextTab => "Next Tab",
+            Command::PreviousTab => "Previous Tab",
+            Command::Exit => "Exit",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::UndoHistory => "Undo History...",
+            Command::FindReplace => "Find & Replace",
+            Command::FindInFiles => "Find in Files...",
+            Command::FindNext => "Find Next",
+            Command::FindPrevious => "Find Previous",
+            Command::GoTo => "Go To...",
+            Command::WordWrap => "Word Wrap",
+            Command::Font => "Font...",
+            Command::StatusBar => "Status Bar",
+            Command::LineNumbers => "Line Numbers",
+            Command::ZoomIn => "Zoom In",
+            Command::ZoomOut => "Zoom Out",
+            Command::ZoomReset => "Restore Default Zoom",
+            Command::Theme => "Theme...",
+            Command::KeyboardShortcuts => "Keyboard Shortcuts...",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Command> {
+        Command::ALL.into_iter().find(|c| c.id() == id)
+    }
+
+    // The first is the one shown in menus
+    fn default_bindings(self) -> &'static [&'static str] {
+        match self {
+            Command::NewFile => &["Ctrl+N"],
+            Command::OpenFile => &["Ctrl+O"],
+            Command::Save => &["Ctrl+S"],
+            Command::SaveAs => &["Ctrl+Shift+S"],
+            Command::CloseTab => &["Ctrl+W"],
+            Command::ReopenClosedTab => &["Ctrl+Shift+T"],
+            Command::NextTab => &["Ctrl+Tab"],
+            Command::PreviousTab => &["Ctrl+Shift+Tab"],
+            Command::Undo => &["Ctrl+Z"],
+            Command::Redo => &["Ctrl+Y", "Ctrl+Shift+Z"],
+            Command::FindReplace => &["Ctrl+H", "Ctrl+F"],
+            Command::FindInFiles => &["Ctrl+Shift+F"],
+            Command::FindNext => &["F3"],
+            Command::FindPrevious => &["Shift+F3"],
+            Command::GoTo => &["Ctrl+G"],
+            // Ctrl+Shift+= is Ctrl++ on most layouts
+            Command::ZoomIn => &["Ctrl+=", "Ctrl+Shift+="],
+            Command::ZoomOut => &["Ctrl+-"],
+            Command::ZoomReset => &["Ctrl+0"],
+            _ => &[],
+        }
+    }
+}
+
+const KEYS: [Key; 73] = [
+    Key::ArrowDown, Key::ArrowLeft, Key::ArrowRight, Key::ArrowUp,
+    Key::Escape, Key::Tab, Key::Backspace, Key::Enter, Key::Space,
+    Key::Insert, Key::Delete, Key::Home, Key::End, Key::PageUp, Key::PageDown,
+    Key::Minus, Key::PlusEquals,
+    Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10,
+    Key::F11, Key::F12, Key::F13, Key::F14, Key::F15, Key::F16, Key::F17, Key::F18, Key::F19, Key::F20,
+];
+
+// A key with its modifiers. Ctrl is Cmd on macOS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: Key,
+}
+
+impl Chord {
+    fn new(key: Key, modifiers: egui::Modifiers) -> Self {
+        Self {
+            ctrl: modifiers.command,
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+            key,
+        }
+    }
+
+    // e.g. "Ctrl+Shift+S"; names are case-insensitive
+    fn parse(text: &str) -> Result<Chord, String> {
+        // "Ctrl++" is Ctrl with the plus key
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        let mut chord = Chord {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            key: parse_key(key.trim()).ok_or_else(|| format!("\"{}\" is not a key", key.trim()))?,
+        };
+        for modifier in modifiers.split('+').map(str::trim).filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => chord.ctrl = true,
+                "alt" | "option" => chord.alt = true,
+                "shift" => chord.shift = true,
+                _ => return Err(format!("\"{}\" is not a modifier (Ctrl, Alt or Shift)", modifier)),
+            }
+        }
+        Ok(chord)
+    }
+
+    // Can't be typed once bound, e.g. a letter on its own
+    fn takes_text(&self) -> bool {
+        !self.ctrl
+            && !self.alt
+            && (self.key.name().len() == 1 || matches!(self.key, Key::Space | Key::Minus | Key::PlusEquals))
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+        f.write_str(key_name(self.key))
+    }
+}
+
+fn key_name(key: Key) -> &'static str {
+    match key {
+        Key::PlusEquals => "=",
+        Key::Minus => "-",
+        key => key.name(),
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    match name.to_lowercase().as_str() {
+        "=" | "+" | "plus" | "equals" => Some(Key::PlusEquals),
+        "-" | "minus" => Some(Key::Minus),
+        "esc" => Some(Key::Escape),
+        "return" => Some(Key::Enter),
+        "del" => Some(Key::Delete),
+        "ins" => Some(Key::Insert),
+        name => KEYS.into_iter().find(|key| key.name().eq_ignore_ascii_case(name)),
+    }
+}
+
+// Chords separated by spaces, pressed one after the other
+fn parse_sequence(text: &str) -> Result<Vec<Chord>, String> {
+    let sequence = text.split_whitespace().map(Chord::parse).collect::<Result<Vec<_>, _>>()?;
+    if sequence.is_empty() {
+        return Err("Empty key binding".to_owned());
+    }
+    Ok(sequence)
+}
+
+pub fn format_sequence(sequence: &[Chord]) -> String {
+    sequence.iter().map(Chord::to_string).collect::<Vec<_>>().join(" ")
+}
+
+pub fn keymap_path() -> Option<PathBuf> {
+    eframe::storage_dir("rpad").map(|dir| dir.join("keymap.toml"))
+}
+
+const KEYMAP_HEADER: &str = "\
+# rpad key bindings. Each line binds a chord, or a sequence of chords separated by spaces,
+# to a command, e.g. \"Ctrl+K Ctrl+S\" = \"file.save\". Ctrl is Cmd on macOS.
+# These are added to the built-in bindings; bind a chord to \"\" to free it.
+# Commands: {commands}
+
+[bindings]
+";
+
+pub struct Keymap {
+    // In the order they were added; a command's first binding is the one shown in menus
+    bindings: Vec<(Vec<Chord>, Command)>,
+    // Chords typed so far of a multi-chord binding
+    pending: Vec<Chord>,
+    // Unreadable entries and bindings that get in each other's way
+    pub problems: Vec<String>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut bindings = Vec::new();
+        for command in Command::ALL {
+            for text in command.default_bindings() {
+                if let Ok(sequence) = parse_sequence(text) {
+                    bindings.push((sequence, command));
+                }
+            }
+        }
+        Self {
+            bindings,
+            pending: Vec::new(),
+            problems: Vec::new(),
+        }
+    }
+}
+
+impl Keymap {
+    // The built-in bindings with the keymap file's on top
+    pub fn load() -> Self {
+        let mut keymap = Self::default();
+        let Some(path) = keymap_path() else {
+            return keymap;
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => keymap.apply_file(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => keymap.problems.push(format!("Failed to read {}: {}", path.display(), e)),
+        }
+        keymap.find_conflicts();
+        keymap
+    }
+
+    fn apply_file(&mut self, text: &str) {
+        let table: toml::Table = match toml::from_str(text) {
+            Ok(table) => table,
+            Err(e) => {
+                self.problems.push(format!("Keymap file: {}", e.message()));
+                return;
+            }
+        };
+        let Some(bindings) = table.get("bindings").and_then(|b| b.as_table()) else {
+            return;
+        };
+        let mut from_file: HashSet<Vec<Chord>> = HashSet::new();
+        for (keys, command) in bindings {
+            let sequence = match parse_sequence(keys) {
+                Ok(sequence) => sequence,
+                Err(e) => {
+                    self.problems.push(format!("Keymap \"{}\": {}", keys, e));
+                    continue;
+                }
+            };
+            let command = match command.as_str() {
+                Some("") => None,
+                Some(id) => match Command::from_id(id) {
+                    Some(command) => Some(command),
+                    None => {
+                        self.problems.push(format!("Keymap \"{}\": no command called \"{}\"", keys, id));
+                        continue;
+                    }
+                },
+                None => {
+                    self.problems.push(format!("Keymap \"{}\": expected a command name in quotes", keys));
+                    continue;
+                }
+            };
+            // Two spellings of one chord, e.g. "ctrl+s" and "Ctrl+S"
+            if !from_file.insert(sequence.clone()) {
+                self.problems.push(format!(
+                    "Keymap: {} is bound more than once; the last one wins",
+                    format_sequence(&sequence)
+                ));
+            }
+            self.bindings.retain(|(bound, _)| *bound != sequence);
+            if let Some(command) = command {
+                self.bindings.push((sequence, command));
+            }
+        }
+    }
+
+    fn find_conflicts(&mut self) {
+        for (sequence, command) in &self.bindings {
+            // A shorter binding fires before the longer one can be finished
+            for (longer, other) in &self.bindings {
+                if longer.len() > sequence.len() && longer.starts_with(sequence) {
+                    self.problems.push(format!(
+                        "Keymap: {} ({}) can never be pressed because {} runs {} first",
+                        format_sequence(longer),
+                        other.id(),
+                        format_sequence(sequence),
+                        command.id()
+                    ));
+                }
+            }
+            if sequence[0].takes_text() {
+                self.problems.push(format!(
+                    "Keymap: {} ({}) stops \"{}\" from being typed",
+                    format_sequence(sequence),
+                    command.id(),
+                    sequence[0]
+                ));
+            }
+        }
+    }
+
+    // Every binding of each command, for the shortcuts list
+    pub fn bindings(&self, command: Command) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, c)| *c == command)
+            .map(|(sequence, _)| format_sequence(sequence))
+            .collect()
+    }
+
+    // The binding shown for the command in menus and tooltips
+    pub fn shortcut(&self, command: Command) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, c)| *c == command)
+            .map(|(sequence, _)| format_sequence(sequence))
+    }
+
+    // The command's title with its shortcut, for menus
+    pub fn label(&self, command: Command) -> String {
+        match self.shortcut(command) {
+            Some(shortcut) => format!("{}\t{}", command.title(), shortcut),
+            None => command.title().to_owned(),
+        }
+    }
+
+    // The first part of a sequence the user is in the middle of typing
+    pub fn pending(&self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| format_sequence(&self.pending))
+    }
+
+    // Takes bound key presses out of this frame's input, so the editor never sees them,
+    // and returns the commands they complete
+    pub fn dispatch(&mut self, input: &mut egui::InputState) -> Vec<Command> {
+        let mut commands = Vec::new();
+        input.events.retain(|event| {
+            let egui::Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } = event
+            else {
+                return true;
+            };
+            let mut sequence = std::mem::take(&mut self.pending);
+            sequence.push(Chord::new(*key, *modifiers));
+            if let Some((_, command)) = self.bindings.iter().find(|(bound, _)| *bound == sequence) {
+                commands.push(*command);
+                return false;
+            }
+            if self.bindings.iter().any(|(bound, _)| bound.starts_with(&sequence)) {
+                self.pending = sequence;
+                return false;
+            }
+            // A key that breaks off a sequence is swallowed along with it
+            sequence.len() == 1
+        });
+        commands
+    }
+
+    // A keymap file to start from, listing the built-in bindings
+    pub fn template() -> String {
+        let commands = Command::ALL.map(Command::id).join(", ");
+        let mut text = KEYMAP_HEADER.replace("{commands}", &commands);
+        for command in Command::ALL {
+            for binding in command.default_bindings() {
+                text.push_str(&format!("\"{}\" = \"{}\"\n", binding, command.id()));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Modifiers;
+
+    fn chord(text: &str) -> Chord {
+        Chord::parse(text).unwrap()
+    }
+
+    // The built-in bindings with `file` applied on top, as Keymap::load does
+    fn with_file(file: &str) -> Keymap {
+        let mut keymap = Keymap::default();
+        keymap.apply_file(file);
+        keymap.find_conflicts();
+        keymap
+    }
+
+    fn press(key: Key, modifiers: Modifiers) -> egui::Event {
+        egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    // Runs one frame's key presses through the keymap; returns the commands and the events left
+    fn dispatch(keymap: &mut Keymap, events: Vec<egui::Event>) -> (Vec<Command>, usize) {
+        let mut input = egui::InputState::default();
+        input.events = events;
+        let commands = keymap.dispatch(&mut input);
+        (commands, input.events.len())
+    }
+
+    const CTRL: Modifiers = Modifiers::COMMAND;
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..Modifiers::COMMAND
+    };
+
+    #[test]
+    fn parses_chords() {
+        let save_as = Chord {
+            ctrl: true,
+            alt: false,
+            shift: true,
+            key: Key::S,
+        };
+        assert_eq!(chord("Ctrl+Shift+S"), save_as);
+        // Any order, any case, Cmd for Ctrl, spaces around the +
+        assert_eq!(chord("shift+ctrl+s"), save_as);
+        assert_eq!(chord("Cmd + Shift + S"), save_as);
+        assert_eq!(chord("F3").key, Key::F3);
+        assert!(!chord("F3").ctrl);
+        assert_eq!(chord("Alt+Esc").key, Key::Escape);
+        assert!(chord("Alt+Esc").alt);
+    }
+
+    #[test]
+    fn plus_and_equals() {
+        assert_eq!(chord("Ctrl++"), chord("Ctrl+="));
+        assert_eq!(chord("Ctrl+Plus").key, Key::PlusEquals);
+        let zoom_in = chord("Ctrl+Shift+=");
+        assert!(zoom_in.ctrl && zoom_in.shift);
+        assert_eq!(zoom_in.key, Key::PlusEquals);
+        assert_eq!(chord("Ctrl+-").key, Key::Minus);
+        // Shown the way they are written in the keymap file
+        assert_eq!(zoom_in.to_string(), "Ctrl+Shift+=");
+        assert_eq!(chord("ctrl+minus").to_string(), "Ctrl+-");
+    }
+
+    #[test]
+    fn rejects_bad_chords() {
+        assert!(Chord::parse("Ctrl+Nope").is_err());
+        assert!(Chord::parse("Hyper+S").is_err());
+        assert!(Chord::parse("").is_err());
+        assert!(parse_sequence("  ").is_err());
+        assert_eq!(parse_sequence("Ctrl+K Ctrl+S").unwrap(), [chord("Ctrl+K"), chord("Ctrl+S")]);
+    }
+
+    #[test]
+    fn defaults_and_template_agree() {
+        let defaults = Keymap::default();
+        assert!(with_file("").problems.is_empty());
+        let from_template = with_file(&Keymap::template());
+        assert!(from_template.problems.is_empty(), "{:?}", from_template.problems);
+        for command in Command::ALL {
+            assert_eq!(from_template.bindings(command), defaults.bindings(command));
+        }
+        assert_eq!(defaults.label(Command::Save), "Save\tCtrl+S");
+        assert_eq!(defaults.label(Command::Exit), "Exit");
+    }
+
+    #[test]
+    fn file_overrides_defaults() {
+        let keymap = with_file("[bindings]\n\"Ctrl+G\" = \"\"\n\"Alt+G\" = \"search.go_to\"\n\"Ctrl+Shift+T\" = \"view.theme\"\n");
+        assert!(keymap.problems.is_empty(), "{:?}", keymap.problems);
+        assert_eq!(keymap.bindings(Command::GoTo), ["Alt+G"]);
+        assert_eq!(keymap.shortcut(Command::Theme).as_deref(), Some("Ctrl+Shift+T"));
+        assert!(keymap.bindings(Command::ReopenClosedTab).is_empty());
+    }
+
+    #[test]
+    fn reports_problems() {
+        let keymap = with_file("[bindings]\n\"Ctrl+Q\" = \"no.such\"\n\"Ctrl+Nope\" = \"file.save\"\n\"Ctrl+1\" = 3\n");
+        assert_eq!(keymap.problems.len(), 3, "{:?}", keymap.problems);
+
+        let keymap = with_file("[bindings]\n\"ctrl+j\" = \"file.new\"\n\"Ctrl+J\" = \"file.open\"\n");
+        assert_eq!(keymap.problems.len(), 1);
+        assert_eq!(keymap.bindings(Command::OpenFile), ["Ctrl+O", "Ctrl+J"]);
+        assert_eq!(keymap.bindings(Command::NewFile), ["Ctrl+N"]);
+
+        assert!(!with_file("not toml [").problems.is_empty());
+    }
+
+    #[test]
+    fn finds_conflicts() {
+        // Ctrl+K alone would fire before Ctrl+K Ctrl+S could be finished
+        let keymap = with_file("[bindings]\n\"Ctrl+K\" = \"view.theme\"\n\"Ctrl+K Ctrl+S\" = \"file.save\"\n");
+        assert_eq!(keymap.problems.len(), 1, "{:?}", keymap.problems);
+        assert!(keymap.problems[0].contains("Ctrl+K Ctrl+S (file.save) can never be pressed"));
+
+        // Sequences that only share a prefix are fine
+        let keymap = with_file("[bindings]\n\"Ctrl+K Ctrl+S\" = \"file.save\"\n\"Ctrl+K Ctrl+T\" = \"view.theme\"\n");
+        assert!(keymap.problems.is_empty(), "{:?}", keymap.problems);
+
+        // Keys that type text
+        for key in ["A", "Shift+A", "Space", "-"] {
+            let keymap = with_file(&format!("[bindings]\n\"{}\" = \"view.theme\"\n", key));
+            assert_eq!(keymap.problems.len(), 1, "{}", key);
+        }
+        assert!(with_file("[bindings]\n\"Alt+A\" = \"view.theme\"\n").problems.is_empty());
+    }
+
+    #[test]
+    fn dispatches_single_chords() {
+        let mut keymap = Keymap::default();
+        let events = vec![
+            press(Key::S, CTRL),
+            egui::Event::Text("x".to_owned()),
+            press(Key::A, Modifiers::NONE),
+            press(Key::PlusEquals, CTRL_SHIFT),
+        ];
+        let (commands, left) = dispatch(&mut keymap, events);
+        assert_eq!(commands, [Command::Save, Command::ZoomIn]);
+        // The text and the unbound key are left for the editor
+        assert_eq!(left, 2);
+
+        // Releases and modifiers that don't match are ignored
+        let release = egui::Event::Key {
+            key: Key::S,
+            pressed: false,
+            repeat: false,
+            modifiers: CTRL,
+        };
+        let (commands, left) = dispatch(&mut keymap, vec![release, press(Key::S, Modifiers::ALT)]);
+        assert!(commands.is_empty());
+        assert_eq!(left, 2);
+    }
+
+    #[test]
+    fn dispatches_sequences() {
+        let mut keymap = with_file("[bindings]\n\"Ctrl+K Ctrl+S\" = \"view.theme\"\n");
+        // Finished within one frame
+        let (commands, left) = dispatch(&mut keymap, vec![press(Key::K, CTRL), press(Key::S, CTRL)]);
+        assert_eq!((commands, left), (vec![Command::Theme], 0));
+
+        // Or across frames, with the first chord shown as pending in between
+        let (commands, _) = dispatch(&mut keymap, vec![press(Key::K, CTRL)]);
+        assert!(commands.is_empty());
+        assert_eq!(keymap.pending().as_deref(), Some("Ctrl+K"));
+        let (commands, _) = dispatch(&mut keymap, vec![press(Key::S, CTRL)]);
+        assert_eq!(commands, [Command::Theme]);
+        assert!(keymap.pending().is_none());
+
+        // A wrong second chord cancels the sequence and is swallowed with it
+        let (commands, left) = dispatch(&mut keymap, vec![press(Key::K, CTRL), press(Key::N, CTRL)]);
+        assert!(commands.is_empty());
+        assert_eq!(left, 0);
+        assert!(keymap.pending().is_none());
+        // After which single chords work again
+        let (commands, _) = dispatch(&mut keymap, vec![press(Key::N, CTRL)]);
+        assert_eq!(commands, [Command::NewFile]);
+    }
+}
@@ -0,0 +1,128 @@
+// src/line_ending.rs
+use std::borrow::Cow;
+
+// Buffers always hold '\n'; the document's line ending is applied when it is written out
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl Default for LineEnding {
+    fn default() -> Self {
+        if cfg!(windows) { Self::CrLf } else { Self::Lf }
+    }
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 3] = [LineEnding::CrLf, LineEnding::Lf, LineEnding::Cr];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "Unix (LF)",
+            LineEnding::CrLf => "Windows (CRLF)",
+            LineEnding::Cr => "Macintosh (CR)",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            LineEnding::Lf => Cow::Borrowed(text),
+            _ => Cow::Owned(text.replace('\n', self.as_str())),
+        }
+    }
+}
+
+// The most common line ending in the text, and whether more than one kind was found
+pub fn detect(text: &str) -> (Option<LineEnding>, bool) {
+    let (mut lf, mut crlf, mut cr) = (0usize, 0usize, 0usize);
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                crlf += 1;
+                i += 1;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let kinds = [lf, crlf, cr].iter().filter(|&&n| n > 0).count();
+    let dominant = if kinds == 0 {
+        None
+    } else if crlf >= lf && crlf >= cr {
+        Some(LineEnding::CrLf)
+    } else if lf >= cr {
+        Some(LineEnding::Lf)
+    } else {
+        Some(LineEnding::Cr)
+    };
+    (dominant, kinds > 1)
+}
+
+pub fn normalize(text: &str) -> Cow<'_, str> {
+    if text.contains('\r') {
+        Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What opening and then saving the text unchanged writes back
+    fn round_trip(text: &str) -> String {
+        let (ending, _) = detect(text);
+        ending.unwrap_or(LineEnding::Lf).apply(&normalize(text)).into_owned()
+    }
+
+    #[test]
+    fn single_kind_round_trips() {
+        for ending in LineEnding::ALL {
+            let text = ["one", "two", "", "three", ""].join(ending.as_str());
+            assert!(detect(&text) == (Some(ending), false));
+            assert_eq!(normalize(&text), "one\ntwo\n\nthree\n");
+            assert_eq!(round_trip(&text), text);
+        }
+    }
+
+    #[test]
+    fn no_line_endings() {
+        assert!(detect("one line") == (None, false));
+        assert!(detect("") == (None, false));
+        assert!(matches!(normalize("one line"), Cow::Borrowed(_)));
+        assert!(matches!(LineEnding::Lf.apply("a\nb"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn mixed() {
+        // CRLF is one line ending, not a CR and an LF
+        let text = "a\r\nb\r\nc\nd";
+        assert!(detect(text) == (Some(LineEnding::CrLf), true));
+        assert_eq!(normalize(text), "a\nb\nc\nd");
+        assert_eq!(round_trip(text), "a\r\nb\r\nc\r\nd");
+
+        let text = "a\rb\nc\rd\r";
+        assert!(detect(text) == (Some(LineEnding::Cr), true));
+        assert_eq!(round_trip(text), "a\rb\rc\rd\r");
+
+        // "\n\r" is an LF followed by a CR
+        assert!(detect("a\n\rb") == (Some(LineEnding::Lf), true));
+        assert_eq!(normalize("a\n\rb"), "a\n\nb");
+    }
+}
@@ -0,0 +1,147 @@
+// src/notifications.rs
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const ERROR_TOAST_DURATION: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+struct Entry {
+    level: Level,
+    message: String,
+    at: Instant,
+}
+
+// Toasts for the user, plus a history of every message shown this session
+pub struct Notifications {
+    started: Instant,
+    log: Vec<Entry>,
+    // Indices into `log` that are still shown as toasts
+    toasts: Vec<usize>,
+    pub show_log: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            log: Vec::new(),
+            toasts: Vec::new(),
+            show_log: false,
+        }
+    }
+}
+
+impl Notifications {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Level::Info, message.into());
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Level::Warning, message.into());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Level::Error, message.into());
+    }
+
+    // Error messages so far, oldest first
+    pub fn errors(&self) -> impl Iterator<Item = &str> {
+        self.log
+            .iter()
+            .filter(|e| e.level == Level::Error)
+            .map(|e| e.message.as_str())
+    }
+
+    fn push(&mut self, level: Level, message: String) {
+        // Repeated failures (e.g. autosave every 30s) shouldn't stack up identical toasts
+        if self.toasts.iter().any(|&i| self.log[i].message == message) {
+            return;
+        }
+        self.log.push(Entry {
+            level,
+            message,
+            at: Instant::now(),
+        });
+        self.toasts.push(self.log.len() - 1);
+    }
+
+    pub fn show_toasts(&mut self, ctx: &egui::Context) {
+        let log = &self.log;
+        self.toasts.retain(|&i| log[i].at.elapsed() < duration(log[i].level));
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for &index in &self.toasts {
+                    let entry = &self.log[index];
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.colored_label(color(ui, entry.level), &entry.message);
+                            if ui.small_button("x").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.retain(|&i| i != index);
+        }
+        ctx.request_repaint_after(Duration::from_millis(500));
+    }
+
+    pub fn show_log(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_log;
+        egui::Window::new("Message Log")
+            .open(&mut open)
+            .default_size([420.0, 240.0])
+            .show(ctx, |ui| {
+                if ui.button("Clear").clicked() {
+                    self.log.clear();
+                    self.toasts.clear();
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                    if self.log.is_empty() {
+                        ui.weak("No messages.");
+                    }
+                    for entry in &self.log {
+                        let elapsed = entry.at.duration_since(self.started).as_secs();
+                        ui.horizontal_wrapped(|ui| {
+                            ui.monospace(format!("{:02}:{:02}:{:02}", elapsed / 3600, elapsed / 60 % 60, elapsed % 60));
+                            ui.colored_label(color(ui, entry.level), &entry.message);
+                        });
+                    }
+                });
+            });
+        self.show_log = open;
+    }
+}
+
+fn duration(level: Level) -> Duration {
+    match level {
+        Level::Error => ERROR_TOAST_DURATION,
+        _ => TOAST_DURATION,
+    }
+}
+
+fn color(ui: &egui::Ui, level: Level) -> egui::Color32 {
+    match level {
+        Level::Info => ui.visuals().text_color(),
+        Level::Warning => ui.visuals().warn_fg_color,
+        Level::Error => ui.visuals().error_fg_color,
+    }
+}
@@ -0,0 +1,52 @@
+// src/preferences.rs
+use crate::fileio::BackupMode;
+use crate::search::{SearchHistory, SearchOptions};
+use crate::theme::ThemeChoice;
+
+pub const DEFAULT_FONT_SIZE: f32 = 14.0;
+
+// Editor zoom limits, as a factor of the font size
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 5.0;
+
+// User settings, persisted under eframe's app key. Document state lives in the session instead.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub font_size: f32,
+    // Font family names; None for the bundled editor font and egui's interface font
+    pub editor_font: Option<String>,
+    pub interface_font: Option<String>,
+    // Editor zoom on top of `font_size`; kept per tab instead when `per_document_zoom` is on
+    pub zoom: f32,
+    pub per_document_zoom: bool,
+    // Scales the whole interface, e.g. for HiDPI screens the system doesn't report correctly
+    pub ui_scale: f32,
+    pub word_wrap: bool,
+    pub status_bar: bool,
+    pub line_numbers: bool,
+    pub theme: ThemeChoice,
+    pub backup: BackupMode,
+    pub search: SearchOptions,
+    pub search_history: SearchHistory,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            font_size: DEFAULT_FONT_SIZE,
+            editor_font: None,
+            interface_font: None,
+            zoom: 1.0,
+            per_document_zoom: false,
+            ui_scale: 1.0,
+            word_wrap: true,
+            status_bar: true,
+            line_numbers: true,
+            theme: ThemeChoice::default(),
+            backup: BackupMode::None,
+            search: SearchOptions::default(),
+            search_history: SearchHistory::default(),
+        }
+    }
+}
@@ -0,0 +1,482 @@
+// src/search.rs
+use eframe::egui;
+use regex::{Captures, Regex, RegexBuilder};
+use std::ops::Range;
+
+// Entries kept in each history dropdown
+const MAX_HISTORY: usize = 25;
+
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub match_case: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+}
+
+// A search the user saved under a name to reuse later
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct SearchPreset {
+    pub name: String,
+    pub find: String,
+    pub replace: String,
+    pub options: SearchOptions,
+}
+
+// Past searches and saved presets, persisted with the preferences. Most recent first.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SearchHistory {
+    pub find: Vec<String>,
+    pub replace: Vec<String>,
+    pub presets: Vec<SearchPreset>,
+}
+
+impl SearchHistory {
+    pub fn remember_find(&mut self, text: &str) {
+        remember(&mut self.find, text);
+    }
+
+    pub fn remember_replace(&mut self, text: &str) {
+        remember(&mut self.replace, text);
+    }
+
+    // A preset with the same name is replaced
+    pub fn save_preset(&mut self, preset: SearchPreset) {
+        self.presets.retain(|p| p.name != preset.name);
+        self.presets.push(preset);
+        self.presets.sort_by_key(|p| p.name.to_lowercase());
+    }
+}
+
+fn remember(history: &mut Vec<String>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    history.retain(|t| t != text);
+    history.insert(0, text.to_owned());
+    history.truncate(MAX_HISTORY);
+}
+
+// A single-line text field with a dropdown of earlier entries
+pub fn history_field(ui: &mut egui::Ui, text: &mut String, history: &[String]) -> egui::Response {
+    let response = ui.text_edit_singleline(text);
+    ui.add_enabled_ui(!history.is_empty(), |ui| {
+        ui.menu_button("⏷", |ui| {
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for entry in history {
+                    let label: String = entry.chars().take(60).collect();
+                    if ui.button(label.replace('\n', "⏎")).clicked() {
+                        entry.clone_into(text);
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+    });
+    response
+}
+
+// Fields a preset is loaded into and saved from
+pub struct PresetTarget<'a> {
+    pub find: &'a mut String,
+    pub replace: &'a mut String,
+    pub options: &'a mut SearchOptions,
+}
+
+// The Presets menu: load or delete a saved search, or save the current one under `new_name`
+pub fn presets_menu(ui: &mut egui::Ui, history: &mut SearchHistory, new_name: &mut String, target: PresetTarget) {
+    ui.menu_button("Presets", |ui| {
+        let mut delete = None;
+        for (index, preset) in history.presets.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let response = ui.button(&preset.name).on_hover_text(format!("Find: {}\nReplace: {}", preset.find, preset.replace));
+                if response.clicked() {
+                    preset.find.clone_into(target.find);
+                    preset.replace.clone_into(target.replace);
+                    *target.options = preset.options;
+                    ui.close_menu();
+                }
+                if ui.small_button("x").on_hover_text("Delete this preset").clicked() {
+                    delete = Some(index);
+                }
+            });
+        }
+        if let Some(index) = delete {
+            history.presets.remove(index);
+        }
+        if !history.presets.is_empty() {
+            ui.separator();
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(new_name).hint_text("Preset name").desired_width(140.0));
+            let can_save = !new_name.trim().is_empty() && !target.find.is_empty();
+            if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() {
+                history.save_preset(SearchPreset {
+                    name: new_name.trim().to_owned(),
+                    find: target.find.clone(),
+                    replace: target.replace.clone(),
+                    options: *target.options,
+                });
+                new_name.clear();
+                ui.close_menu();
+            }
+        });
+    });
+}
+
+pub struct Replacement {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+// One row of the Replace All preview
+pub struct PreviewItem {
+    pub replacement: Replacement,
+    // 1-based
+    pub line: usize,
+    pub checked: bool,
+}
+
+// A find pattern compiled with its options. Plain text is escaped into a regex so every mode
+// shares one matcher.
+#[derive(Clone)]
+pub struct Query {
+    regex: Regex,
+    options: SearchOptions,
+}
+
+impl Query {
+    // None for an empty pattern; Err with a message for an invalid regex
+    pub fn new(pattern: &str, options: SearchOptions) -> Result<Option<Self>, String> {
+        if pattern.is_empty() {
+            return Ok(None);
+        }
+        let source = if options.regex { pattern.to_owned() } else { regex::escape(pattern) };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(!options.match_case)
+            .multi_line(true)
+            .build()
+            .map_err(|e| match e {
+                regex::Error::Syntax(message) => message,
+                e => e.to_string(),
+            })?;
+        Ok(Some(Self { regex, options }))
+    }
+
+    // Byte ranges of every non-empty match in `text`, or only those inside `scope`
+    pub fn find_all(&self, text: &str, scope: Option<Range<usize>>) -> Vec<Range<usize>> {
+        self.captures(text)
+            .map(|c| c.get(0).unwrap().range())
+            .filter(|m| in_scope(m, &scope))
+            .collect()
+    }
+
+    // Every match with the text it would be replaced by. In regex mode the replacement
+    // expands $1 and ${name} and understands \n, \t and \\.
+    pub fn replacements(&self, text: &str, replacement: &str, scope: Option<Range<usize>>) -> Vec<Replacement> {
+        let replacement = if self.options.regex { unescape(replacement) } else { replacement.to_owned() };
+        self.captures(text)
+            .filter(|c| in_scope(&c.get(0).unwrap().range(), &scope))
+            .map(|captures| {
+                let mut result = String::new();
+                if self.options.regex {
+                    captures.expand(&replacement, &mut result);
+                } else {
+                    result.clone_from(&replacement);
+                }
+                Replacement {
+                    range: captures.get(0).unwrap().range(),
+                    text: result,
+                }
+            })
+            .collect()
+    }
+
+    // Empty matches (e.g. from x*, ^ or $) are skipped: there is nothing to select or count, and
+    // replacing them would insert text at every position they match
+    fn captures<'t>(&'t self, text: &'t str) -> impl Iterator<Item = Captures<'t>> + 't {
+        self.regex.captures_iter(text).filter(move |c| {
+            let range = c.get(0).unwrap().range();
+            !range.is_empty() && (!self.options.whole_word || is_whole_word(text, range))
+        })
+    }
+}
+
+fn in_scope(range: &Range<usize>, scope: &Option<Range<usize>>) -> bool {
+    scope.as_ref().is_none_or(|scope| range.start >= scope.start && range.end <= scope.end)
+}
+
+// Checked after matching rather than with \b, which misbehaves when the pattern itself starts
+// or ends with punctuation
+fn is_whole_word(text: &str, range: Range<usize>) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    !text[..range.start].chars().next_back().is_some_and(is_word) && !text[range.end..].chars().next().is_some_and(is_word)
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+// `text` with the given replacements (in order, not overlapping) made
+pub fn apply(text: &str, replacements: &[Replacement]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut end = 0;
+    for replacement in replacements {
+        result.push_str(&text[end..replacement.range.start]);
+        result.push_str(&replacement.text);
+        end = replacement.range.end;
+    }
+    result.push_str(&text[end..]);
+    result
+}
+
+pub fn preview(text: &str, replacements: Vec<Replacement>) -> Vec<PreviewItem> {
+    let mut line = 1;
+    let mut counted = 0;
+    replacements
+        .into_iter()
+        .map(|replacement| {
+            line += text[counted..replacement.range.start].matches('\n').count();
+            counted = replacement.range.start;
+            PreviewItem {
+                replacement,
+                line,
+                checked: true,
+            }
+        })
+        .collect()
+}
+
+// The line around a replacement with the removed text struck out and the new text after it
+pub fn preview_job(ui: &egui::Ui, text: &str, replacement: &Replacement) -> egui::text::LayoutJob {
+    const CONTEXT: usize = 40;
+    let range = &replacement.range;
+    let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[range.end..].find('\n').map_or(text.len(), |i| range.end + i);
+    let before = &text[line_start..range.start];
+    let after = &text[range.end..line_end];
+    let before_chars = before.chars().count();
+    let before: String = before.chars().skip(before_chars.saturating_sub(CONTEXT)).collect();
+
+    let format = egui::TextFormat {
+        font_id: egui::TextStyle::Monospace.resolve(ui.style()),
+        color: ui.visuals().text_color(),
+        ..Default::default()
+    };
+    let visible = |s: &str| s.replace('\n', "⏎").replace('\t', "→");
+    let mut job = egui::text::LayoutJob::default();
+    if before_chars > CONTEXT {
+        job.append("…", 0.0, format.clone());
+    }
+    job.append(&visible(&before), 0.0, format.clone());
+    job.append(
+        &visible(&text[range.clone()]),
+        0.0,
+        egui::TextFormat {
+            background: egui::Color32::from_rgb(200, 60, 60).gamma_multiply(0.4),
+            strikethrough: egui::Stroke::new(1.0, format.color),
+            ..format.clone()
+        },
+    );
+    job.append(
+        &visible(&replacement.text),
+        0.0,
+        egui::TextFormat {
+            background: egui::Color32::from_rgb(60, 160, 60).gamma_multiply(0.4),
+            ..format.clone()
+        },
+    );
+    let after_chars = after.chars().count();
+    job.append(&visible(&after.chars().take(CONTEXT).collect::<String>()), 0.0, format.clone());
+    if after_chars > CONTEXT {
+        job.append("…", 0.0, format);
+    }
+    job
+}
+
+// The match to select next, and whether the search went past the end (or start) to get there
+pub fn next_match(matches: &[Range<usize>], selection: Range<usize>, forward: bool) -> Option<(usize, bool)> {
+    if matches.is_empty() {
+        return None;
+    }
+    if forward {
+        match matches.iter().position(|m| m.start >= selection.end) {
+            Some(index) => Some((index, false)),
+            None => Some((0, true)),
+        }
+    } else {
+        match matches.iter().rposition(|m| m.start < selection.start) {
+            Some(index) => Some((index, false)),
+            None => Some((matches.len() - 1, true)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(pattern: &str, options: SearchOptions) -> Query {
+        Query::new(pattern, options).unwrap().unwrap()
+    }
+
+    // Clippy takes an array holding one range for a mistake, so lone matches are compared as tuples
+    fn spans(ranges: Vec<Range<usize>>) -> Vec<(usize, usize)> {
+        ranges.into_iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    fn plain() -> SearchOptions {
+        SearchOptions::default()
+    }
+
+    fn regex() -> SearchOptions {
+        SearchOptions { regex: true, ..Default::default() }
+    }
+
+    #[test]
+    fn empty_and_invalid_patterns() {
+        assert!(Query::new("", regex()).unwrap().is_none());
+        assert!(Query::new("(", regex()).is_err());
+        // Only a regex can be invalid
+        assert!(Query::new("(", plain()).unwrap().is_some());
+    }
+
+    #[test]
+    fn plain_text_is_escaped() {
+        let text = "a.b axb (a.b)";
+        assert_eq!(query("a.b", plain()).find_all(text, None), [0..3, 9..12]);
+        assert_eq!(query("a.b", regex()).find_all(text, None), [0..3, 4..7, 9..12]);
+        assert_eq!(spans(query("(a", plain()).find_all(text, None)), [(8, 10)]);
+    }
+
+    #[test]
+    fn match_case() {
+        let text = "Rust rust RUST";
+        assert_eq!(query("rust", plain()).find_all(text, None).len(), 3);
+        let options = SearchOptions { match_case: true, ..Default::default() };
+        assert_eq!(spans(query("rust", options).find_all(text, None)), [(5, 9)]);
+    }
+
+    #[test]
+    fn whole_word() {
+        let options = SearchOptions { whole_word: true, ..Default::default() };
+        let text = "cat concat cat_ cat. catalog über_cat";
+        assert_eq!(query("cat", options).find_all(text, None), [0..3, 16..19]);
+        // Punctuation at the edge of the pattern still works, unlike with \b
+        assert_eq!(spans(query(".x", options).find_all("a.x .x", None)), [(4, 6)]);
+        // Letters outside ASCII are word characters too
+        assert!(query("ber", options).find_all("über", None).is_empty());
+    }
+
+    #[test]
+    fn scope() {
+        let q = query("ab", plain());
+        let text = "ab ab ab";
+        assert_eq!(q.find_all(text, Some(2..8)), [3..5, 6..8]);
+        // A match that only starts inside the scope is left out
+        assert_eq!(spans(q.find_all(text, Some(0..4))), [(0, 2)]);
+        assert_eq!(q.replacements(text, "x", Some(2..6)).len(), 1);
+    }
+
+    #[test]
+    fn replacements() {
+        let q = query(r"(\w+)=(\w+)", regex());
+        let text = "a=1, b=2";
+        let replacements = q.replacements(text, r"$2=$1\n\t\\\q", None);
+        assert_eq!(replacements[0].text, "1=a\n\t\\\\q");
+        assert_eq!(apply(text, &replacements), "1=a\n\t\\\\q, 2=b\n\t\\\\q");
+
+        // Plain text replacements are taken literally
+        let q = query("a", plain());
+        assert_eq!(apply("banana", &q.replacements("banana", r"$0\n", None)), r"b$0\nn$0\nn$0\n");
+    }
+
+    #[test]
+    fn empty_matches_are_skipped() {
+        for pattern in ["x*", "^", "$", r""] {
+            let q = query(pattern, regex());
+            assert!(q.find_all("abc\ndef", None).is_empty(), "{}", pattern);
+            assert!(q.replacements("abc\ndef", "!", None).is_empty(), "{}", pattern);
+        }
+        let q = query("x*", regex());
+        assert_eq!(apply("axxb", &q.replacements("axxb", "-", None)), "a-b");
+    }
+
+    #[test]
+    fn unescapes() {
+        assert_eq!(unescape(r"a\nb\tc\\d"), "a\nb\tc\\d");
+        // Unknown escapes and a trailing backslash are kept as written
+        assert_eq!(unescape(r"\d\"), r"\d\");
+    }
+
+    #[test]
+    fn preview_lines() {
+        let text = "one\ntwo two\nthree";
+        let items = preview(text, query("t", plain()).replacements(text, "T", None));
+        let lines: Vec<usize> = items.iter().map(|item| item.line).collect();
+        assert_eq!(lines, [2, 2, 3]);
+    }
+
+    #[test]
+    fn next_match_wraps() {
+        let matches = [2..4, 10..12, 20..22];
+        assert_eq!(next_match(&matches, 0..0, true), Some((0, false)));
+        // The current selection is skipped
+        assert_eq!(next_match(&matches, 2..4, true), Some((1, false)));
+        assert_eq!(next_match(&matches, 20..22, true), Some((0, true)));
+        assert_eq!(next_match(&matches, 10..12, false), Some((0, false)));
+        assert_eq!(next_match(&matches, 2..4, false), Some((2, true)));
+        assert_eq!(next_match(&[], 0..0, true), None);
+    }
+
+    #[test]
+    fn history() {
+        let mut history = SearchHistory::default();
+        history.remember_find("a");
+        history.remember_find("b");
+        history.remember_find("a");
+        history.remember_find("");
+        assert_eq!(history.find, ["a", "b"]);
+
+        for i in 0..MAX_HISTORY + 5 {
+            history.remember_replace(&i.to_string());
+        }
+        assert_eq!(history.replace.len(), MAX_HISTORY);
+        assert_eq!(history.replace[0], (MAX_HISTORY + 4).to_string());
+    }
+
+    #[test]
+    fn presets_replace_by_name() {
+        let mut history = SearchHistory::default();
+        let preset = |name: &str, find: &str| SearchPreset {
+            name: name.to_owned(),
+            find: find.to_owned(),
+            replace: String::new(),
+            options: SearchOptions::default(),
+        };
+        history.save_preset(preset("todo", "TODO"));
+        history.save_preset(preset("Fixme", "FIXME"));
+        history.save_preset(preset("todo", "TODO|XXX"));
+        let presets: Vec<_> = history.presets.iter().map(|p| (p.name.as_str(), p.find.as_str())).collect();
+        assert_eq!(presets, [("Fixme", "FIXME"), ("todo", "TODO|XXX")]);
+    }
+}
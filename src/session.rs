@@ -0,0 +1,132 @@
+// src/session.rs
+use crate::document::Document;
+use encoding_rs::Encoding;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+pub const SESSION_KEY: &str = "session";
+
+// Which tabs were open and where the user was in each, without their contents
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Session {
+    pub tabs: Vec<SessionTab>,
+    pub active: usize,
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SessionTab {
+    pub path: Option<PathBuf>,
+    // File name in the recovery store, for untitled buffers
+    pub recovery: Option<String>,
+    pub encoding: Option<String>,
+    // Language picked by hand in the status bar
+    pub language: Option<String>,
+    pub zoom: Option<f32>,
+    pub cursor: usize,
+    pub scroll: [f32; 2],
+}
+
+impl Session {
+    pub fn capture(documents: &[Document], active: usize) -> Self {
+        let mut tabs = Vec::new();
+        let mut session_active = 0;
+        for (index, doc) in documents.iter().enumerate() {
+            let recovery = if doc.path.is_none() {
+                if doc.content.is_empty() {
+                    continue;
+                }
+                // Unique per process and document, so two windows never write the same file
+                Some(format!("untitled-{}-{}.txt", std::process::id(), doc.id))
+            } else {
+                None
+            };
+            if index == active {
+                session_active = tabs.len();
+            }
+            tabs.push(SessionTab {
+                path: doc.path.clone(),
+                recovery,
+                encoding: Some(doc.encoding.encoding.name().to_owned()),
+                language: doc.highlighting.language.clone(),
+                zoom: doc.zoom,
+                cursor: doc.cursor,
+                scroll: [doc.scroll.x, doc.scroll.y],
+            });
+        }
+        Self {
+            tabs,
+            active: session_active,
+        }
+    }
+
+    // Re-reads every file from disk; tabs whose file has gone away are dropped
+    pub fn restore(&self) -> (Vec<Document>, usize) {
+        let mut documents = Vec::new();
+        let mut active = 0;
+        for (index, tab) in self.tabs.iter().enumerate() {
+            let encoding = tab.encoding.as_deref().and_then(|name| Encoding::for_label(name.as_bytes()));
+            let mut doc = if let Some(path) = &tab.path {
+                let opened = match encoding {
+                    Some(encoding) => Document::open_with(path.clone(), encoding),
+                    None => Document::open(path.clone()),
+                };
+                match opened {
+                    Ok(doc) => doc,
+                    Err(_) => continue,
+                }
+            } else if let Some(content) = tab.recovery.as_deref().and_then(read_recovery) {
+                Document::untitled(content)
+            } else {
+                continue;
+            };
+            if index == self.active {
+                active = documents.len();
+            }
+            doc.highlighting.language = tab.language.clone();
+            doc.zoom = tab.zoom;
+            doc.cursor = tab.cursor;
+            doc.scroll = egui::vec2(tab.scroll[0], tab.scroll[1]);
+            doc.restore_view = true;
+            doc.pending_selection = Some((tab.cursor, tab.cursor));
+            documents.push(doc);
+        }
+        (documents, active)
+    }
+
+    // Writes untitled buffers to the recovery store. `owned` holds the files this window wrote or
+    // restored from; those no longer referenced are removed, and other windows' files are left alone.
+    pub fn write_recovery(&self, documents: &[Document], owned: &mut HashSet<String>) -> io::Result<()> {
+        let Some(dir) = recovery_dir() else {
+            return Ok(());
+        };
+        let untitled = documents
+            .iter()
+            .filter(|d| d.path.is_none() && !d.content.is_empty());
+        let names = self.tabs.iter().filter_map(|t| t.recovery.as_ref());
+
+        let mut keep = HashSet::new();
+        for (name, doc) in names.zip(untitled) {
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(name), &doc.content)?;
+            keep.insert(name.clone());
+        }
+
+        for name in owned.difference(&keep) {
+            let _ = fs::remove_file(dir.join(name));
+        }
+        *owned = keep;
+        Ok(())
+    }
+}
+
+fn recovery_dir() -> Option<PathBuf> {
+    eframe::storage_dir("rpad").map(|dir| dir.join("recovery"))
+}
+
+fn read_recovery(name: &str) -> Option<String> {
+    fs::read_to_string(recovery_dir()?.join(name)).ok()
+}
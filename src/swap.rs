@@ -0,0 +1,143 @@
+// src/swap.rs
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, TryLockError};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// How often modified buffers are copied into the swap directory
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+const SWAP_EXTENSION: &str = "swp";
+
+// Locked by each running rpad for its whole lifetime, so other instances can tell its swap
+// files apart from ones left behind by a crash
+static LOCK: OnceLock<Option<File>> = OnceLock::new();
+
+// A snapshot of a modified buffer, written periodically so a crash doesn't lose it.
+// Untitled buffers are covered by the session's recovery store instead.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SwapFile {
+    pub path: PathBuf,
+    pub saved_at: u64,
+    pub pid: u32,
+    pub content: String,
+}
+
+impl SwapFile {
+    pub fn age_text(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let minutes = now.saturating_sub(self.saved_at) / 60;
+        match minutes {
+            0 => "less than a minute ago".to_owned(),
+            1 => "1 minute ago".to_owned(),
+            m if m < 60 => format!("{} minutes ago", m),
+            m if m < 60 * 24 => format!("{} hours ago", m / 60),
+            m => format!("{} days ago", m / (60 * 24)),
+        }
+    }
+}
+
+fn swap_dir() -> Option<PathBuf> {
+    eframe::storage_dir("rpad").map(|dir| dir.join("swap"))
+}
+
+// Swap files are keyed by the canonical path so different spellings of a path share one
+fn swap_path(path: &Path) -> Option<PathBuf> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    Some(swap_dir()?.join(format!("{:016x}.{}", hasher.finish(), SWAP_EXTENSION)))
+}
+
+fn lock_path(pid: u32) -> Option<PathBuf> {
+    Some(swap_dir()?.join(format!("{}.lock", pid)))
+}
+
+fn hold_lock() {
+    LOCK.get_or_init(|| {
+        let path = lock_path(std::process::id())?;
+        fs::create_dir_all(path.parent()?).ok()?;
+        let file = File::create(path).ok()?;
+        file.try_lock().ok()?;
+        Some(file)
+    });
+}
+
+pub fn write(path: &Path, content: &str) -> io::Result<()> {
+    let Some(swap_path) = swap_path(path) else {
+        return Ok(());
+    };
+    hold_lock();
+    let swap = SwapFile {
+        path: path.to_path_buf(),
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        pid: std::process::id(),
+        content: content.to_owned(),
+    };
+    if let Some(dir) = swap_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&swap_path, serde_json::to_string(&swap)?)
+}
+
+pub fn remove(path: &Path) {
+    if let Some(swap_path) = swap_path(path) {
+        let _ = fs::remove_file(swap_path);
+    }
+}
+
+fn read_all() -> Vec<(PathBuf, SwapFile)> {
+    let Some(Ok(entries)) = swap_dir().map(fs::read_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == SWAP_EXTENSION))
+        .filter_map(|p| {
+            let swap = serde_json::from_str(&fs::read_to_string(&p).ok()?).ok()?;
+            Some((p, swap))
+        })
+        .collect()
+}
+
+// Swap files left behind by an rpad process that is no longer running, with where each was read from
+pub fn find_orphans() -> Vec<(PathBuf, SwapFile)> {
+    let own_pid = std::process::id();
+    let mut orphans: Vec<(PathBuf, SwapFile)> = read_all()
+        .into_iter()
+        .filter(|(_, swap)| swap.pid != own_pid && !process_running(swap.pid))
+        .collect();
+    orphans.sort_by_key(|(_, swap)| std::cmp::Reverse(swap.saved_at));
+    orphans
+}
+
+// Called on a clean exit; anything still modified at that point was explicitly discarded
+pub fn remove_own() {
+    let own_pid = std::process::id();
+    for (swap_path, swap) in read_all() {
+        if swap.pid == own_pid {
+            let _ = fs::remove_file(swap_path);
+        }
+    }
+    if let Some(path) = lock_path(own_pid) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// The lock is released by the OS when a process exits, however it exits
+fn process_running(pid: u32) -> bool {
+    let Some(file) = lock_path(pid).and_then(|path| File::open(path).ok()) else {
+        return false;
+    };
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+}
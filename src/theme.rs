@@ -0,0 +1,261 @@
+// src/theme.rs
+use eframe::egui;
+use egui::Color32;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A color written as "#rrggbb" or "#rrggbbaa" in theme files
+#[derive(Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub Color32);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let hex = text.strip_prefix('#').unwrap_or(&text);
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        let color = match hex.len() {
+            6 => channel(0).zip(channel(2)).zip(channel(4)).map(|((r, g), b)| Color32::from_rgb(r, g, b)),
+            8 => channel(0)
+                .zip(channel(2))
+                .zip(channel(4))
+                .zip(channel(6))
+                .map(|(((r, g), b), a)| Color32::from_rgba_unmultiplied(r, g, b, a)),
+            _ => None,
+        };
+        color.map(Color).ok_or_else(|| format!("\"{}\" is not a color like #rrggbb", text))
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.0.to_srgba_unmultiplied();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+}
+
+fn hex(rgb: u32) -> Color {
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Color(Color32::from_rgb(r, g, b))
+}
+
+// Colors for each kind of token the highlighter picks out
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SyntaxColors {
+    pub comment: Color,
+    pub string: Color,
+    pub number: Color,
+    pub constant: Color,
+    pub keyword: Color,
+    pub operator: Color,
+    pub function: Color,
+    #[serde(rename = "type")]
+    pub type_name: Color,
+}
+
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Theme {
+    pub name: String,
+    // Starts from egui's dark widgets rather than its light ones
+    pub dark: bool,
+    pub background: Color,
+    // Menus, panels and dialogs around the editor
+    pub panel: Color,
+    pub text: Color,
+    pub selection: Color,
+    pub current_line: Color,
+    pub gutter_background: Color,
+    pub gutter_text: Color,
+    pub gutter_current_text: Color,
+    pub search_match: Color,
+    pub syntax: SyntaxColors,
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Self {
+            name: "Light".to_owned(),
+            dark: false,
+            background: hex(0xffffff),
+            panel: hex(0xf0f0f0),
+            text: hex(0x1f1f1f),
+            selection: hex(0xadd6ff),
+            current_line: hex(0xeef3fb),
+            gutter_background: hex(0xf5f5f5),
+            gutter_text: hex(0x999999),
+            gutter_current_text: hex(0x333333),
+            search_match: hex(0xf8d66d),
+            syntax: SyntaxColors {
+                comment: hex(0x008000),
+                string: hex(0xa31515),
+                number: hex(0x098658),
+                constant: hex(0x0000ff),
+                keyword: hex(0xaf00db),
+                operator: hex(0x1f1f1f),
+                function: hex(0x795e26),
+                type_name: hex(0x267f99),
+            },
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            name: "Dark".to_owned(),
+            dark: true,
+            background: hex(0x1e1e1e),
+            panel: hex(0x2b2b2b),
+            text: hex(0xd4d4d4),
+            selection: hex(0x264f78),
+            current_line: hex(0x2a2d2e),
+            gutter_background: hex(0x252526),
+            gutter_text: hex(0x858585),
+            gutter_current_text: hex(0xc6c6c6),
+            search_match: hex(0x613214),
+            syntax: SyntaxColors {
+                comment: hex(0x6a9955),
+                string: hex(0xce9178),
+                number: hex(0xb5cea8),
+                constant: hex(0x569cd6),
+                keyword: hex(0xc586c0),
+                operator: hex(0xd4d4d4),
+                function: hex(0xdcdcaa),
+                type_name: hex(0x4ec9b0),
+            },
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "High Contrast".to_owned(),
+            dark: true,
+            background: hex(0x000000),
+            panel: hex(0x000000),
+            text: hex(0xffffff),
+            selection: hex(0x0037c8),
+            current_line: hex(0x262626),
+            gutter_background: hex(0x000000),
+            gutter_text: hex(0xc0c0c0),
+            gutter_current_text: hex(0xffff00),
+            search_match: hex(0x8a4b00),
+            syntax: SyntaxColors {
+                comment: hex(0x3ce63c),
+                string: hex(0xffa0a0),
+                number: hex(0xb0ffb0),
+                constant: hex(0x00ffff),
+                keyword: hex(0xffff00),
+                operator: hex(0xffffff),
+                function: hex(0x87cefa),
+                type_name: hex(0x40ffd0),
+            },
+        }
+    }
+
+    pub fn builtin() -> Vec<Theme> {
+        vec![Self::light(), Self::dark(), Self::high_contrast()]
+    }
+
+    // egui's own widgets in the theme's colors
+    pub fn visuals(&self) -> egui::Visuals {
+        let mut visuals = if self.dark { egui::Visuals::dark() } else { egui::Visuals::light() };
+        visuals.override_text_color = Some(self.text.0);
+        visuals.panel_fill = self.panel.0;
+        visuals.window_fill = self.panel.0;
+        visuals.extreme_bg_color = self.background.0;
+        visuals.faint_bg_color = self.gutter_background.0;
+        visuals.selection.bg_fill = self.selection.0;
+        visuals
+    }
+
+    // Reads a theme file. Colors it leaves out come from the built-in theme named by `base`,
+    // or Light/Dark going by `dark`, so a theme can change just a few colors.
+    pub fn load(path: &Path) -> Result<Theme, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut overlay: toml::Table = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string())?,
+            _ => toml::from_str(&text).map_err(|e| toml_error(&text, e))?,
+        };
+        let base_name = overlay.remove("base");
+        let base = match base_name.as_ref().and_then(|b| b.as_str()) {
+            Some(name) => Theme::builtin()
+                .into_iter()
+                .find(|t| t.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("No built-in theme called \"{}\"", name))?,
+            None if overlay.get("dark").and_then(|d| d.as_bool()) == Some(true) => Theme::dark(),
+            None => Theme::light(),
+        };
+        let mut table: toml::Table = toml::from_str(&base.to_toml()).map_err(|e| e.to_string())?;
+        table.insert("name".to_owned(), file_stem(path).into());
+        merge(&mut table, overlay);
+        toml::Value::Table(table).try_into().map_err(|e: toml::de::Error| e.message().to_owned())
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+// The message and line, without the quoted source toml puts in its Display output
+fn toml_error(text: &str, error: toml::de::Error) -> String {
+    match error.span() {
+        Some(span) => format!("line {}: {}", text[..span.start].lines().count().max(1), error.message().replace('\n', "; ")),
+        None => error.message().to_owned(),
+    }
+}
+
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// Which theme is in use
+#[derive(Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ThemeChoice {
+    // Light or Dark, whichever the desktop uses
+    #[default]
+    FollowSystem,
+    Named(String),
+}
+
+pub fn themes_dir() -> Option<PathBuf> {
+    eframe::storage_dir("rpad").map(|dir| dir.join("themes"))
+}
+
+// The built-in themes followed by the user's *.toml and *.json themes; a file named after a
+// built-in theme replaces it. Files that can't be read are reported rather than skipped silently.
+pub fn load_all() -> (Vec<Theme>, Vec<String>) {
+    let mut themes = Theme::builtin();
+    let mut errors = Vec::new();
+    let Some(entries) = themes_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return (themes, errors);
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("toml" | "json")))
+        .collect();
+    paths.sort();
+    for path in paths {
+        match Theme::load(&path) {
+            Ok(theme) => match themes.iter_mut().find(|t| t.name == theme.name) {
+                Some(existing) => *existing = theme,
+                None => themes.push(theme),
+            },
+            Err(e) => errors.push(format!("Theme {}: {}", path.display(), e)),
+        }
+    }
+    (themes, errors)
+}
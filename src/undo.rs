@@ -0,0 +1,398 @@
+// src/undo.rs
+use std::time::{Duration, Instant};
+
+// Keystrokes closer together than this are undone as one step
+const GROUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditKind {
+    Typing,
+    Command(&'static str),
+}
+
+// Replacing `removed` at byte offset `at` with `inserted` turns the parent state into this one
+struct Edit {
+    at: usize,
+    removed: String,
+    inserted: String,
+}
+
+struct Node {
+    parent: usize,
+    edit: Edit,
+    kind: EditKind,
+    // The branch redo follows: the one most recently created or visited
+    redo_child: Option<usize>,
+    children: Vec<usize>,
+    time: Instant,
+}
+
+// Every state the buffer has been in, as a tree so undoing and then typing never loses a branch
+pub struct UndoHistory {
+    // nodes[0] is the root (the text as loaded) and its edit is empty
+    nodes: Vec<Node>,
+    current: usize,
+    // The text at `current`, to diff against after the editor changes it
+    snapshot: String,
+    // The node matching the file on disk, if any
+    saved: Option<usize>,
+}
+
+pub struct HistoryEntry {
+    pub node: usize,
+    pub depth: usize,
+    pub label: String,
+    pub age: Duration,
+    pub is_current: bool,
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl UndoHistory {
+    pub fn new(text: String) -> Self {
+        Self {
+            nodes: vec![Node {
+                parent: 0,
+                edit: Edit {
+                    at: 0,
+                    removed: String::new(),
+                    inserted: String::new(),
+                },
+                kind: EditKind::Command("Original"),
+                redo_child: None,
+                children: Vec::new(),
+                time: Instant::now(),
+            }],
+            current: 0,
+            snapshot: text,
+            saved: Some(0),
+        }
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.current);
+    }
+
+    // The file on disk no longer matches any state in the history
+    pub fn forget_saved(&mut self) {
+        self.saved = None;
+    }
+
+    pub fn is_at_saved(&self) -> bool {
+        self.saved == Some(self.current)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.current != 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.nodes[self.current].redo_child.is_some()
+    }
+
+    // Records whatever changed between the last known state and `text`
+    pub fn record(&mut self, text: &str, kind: EditKind) {
+        let Some(edit) = diff(&self.snapshot, text) else {
+            return;
+        };
+        apply(&mut self.snapshot, edit.at, &edit.removed, &edit.inserted);
+
+        let now = Instant::now();
+        let node = &mut self.nodes[self.current];
+        // Never merge into the saved state, or undo couldn't get back to it
+        if self.current != 0
+            && self.saved != Some(self.current)
+            && kind == EditKind::Typing
+            && node.kind == EditKind::Typing
+            && node.children.is_empty()
+            && now.duration_since(node.time) < GROUP_TIMEOUT
+            && merge(&mut node.edit, &edit)
+        {
+            node.time = now;
+            return;
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            parent: self.current,
+            edit,
+            kind,
+            redo_child: None,
+            children: Vec::new(),
+            time: now,
+        });
+        let parent = &mut self.nodes[self.current];
+        parent.children.push(index);
+        parent.redo_child = Some(index);
+        self.current = index;
+    }
+
+    // Each returns the byte offset to put the cursor at
+    pub fn undo(&mut self, text: &mut String) -> Option<usize> {
+        let cursor = self.step_up(text)?;
+        self.snapshot.clone_from(text);
+        Some(cursor)
+    }
+
+    pub fn redo(&mut self, text: &mut String) -> Option<usize> {
+        let child = self.nodes[self.current].redo_child?;
+        let cursor = self.step_down(text, child);
+        self.snapshot.clone_from(text);
+        Some(cursor)
+    }
+
+    // Walks up to the common ancestor and back down to `target`, on whichever branch it is
+    pub fn jump_to(&mut self, text: &mut String, target: usize) -> Option<usize> {
+        let mut path = vec![target];
+        while let Some(&last) = path.last()
+            && last != 0
+        {
+            path.push(self.nodes[last].parent);
+        }
+
+        let mut cursor = None;
+        while !path.contains(&self.current) {
+            cursor = self.step_up(text);
+        }
+        let depth = path.iter().position(|&n| n == self.current)?;
+        for &node in path[..depth].iter().rev() {
+            cursor = Some(self.step_down(text, node));
+        }
+        self.snapshot.clone_from(text);
+        cursor
+    }
+
+    fn step_up(&mut self, text: &mut String) -> Option<usize> {
+        if self.current == 0 {
+            return None;
+        }
+        let node = &self.nodes[self.current];
+        let edit = &node.edit;
+        apply(text, edit.at, &edit.inserted, &edit.removed);
+        let cursor = edit.at + edit.removed.len();
+        let parent = node.parent;
+        self.nodes[parent].redo_child = Some(self.current);
+        self.current = parent;
+        Some(cursor)
+    }
+
+    fn step_down(&mut self, text: &mut String, child: usize) -> usize {
+        let edit = &self.nodes[child].edit;
+        apply(text, edit.at, &edit.removed, &edit.inserted);
+        let cursor = edit.at + edit.inserted.len();
+        self.nodes[self.current].redo_child = Some(child);
+        self.current = child;
+        cursor
+    }
+
+    // The whole tree, depth first, for the history browser
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        let mut entries = Vec::new();
+        let mut stack = vec![(0, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            let node = &self.nodes[index];
+            entries.push(HistoryEntry {
+                node: index,
+                depth,
+                label: label(node),
+                age: node.time.elapsed(),
+                is_current: index == self.current,
+            });
+            // Branches off the main line are indented; the first child continues at the same depth
+            for (i, &child) in node.children.iter().enumerate().rev() {
+                stack.push((child, if i == 0 { depth } else { depth + 1 }));
+            }
+        }
+        entries
+    }
+}
+
+fn label(node: &Node) -> String {
+    match node.kind {
+        EditKind::Command(name) => name.to_owned(),
+        EditKind::Typing => {
+            let (verb, text) = if node.edit.inserted.is_empty() {
+                ("Delete", &node.edit.removed)
+            } else {
+                ("Type", &node.edit.inserted)
+            };
+            let mut preview: String = text.chars().take(24).collect();
+            if preview.len() < text.len() {
+                preview.push('…');
+            }
+            format!("{} \"{}\"", verb, preview.replace('\n', "⏎").replace('\t', "→"))
+        }
+    }
+}
+
+fn apply(text: &mut String, at: usize, from: &str, to: &str) {
+    text.replace_range(at..at + from.len(), to);
+}
+
+// Extends a typing step with the next keystroke if they are adjacent
+fn merge(previous: &mut Edit, next: &Edit) -> bool {
+    if next.inserted.contains('\n') {
+        return false;
+    }
+    // Typing forwards
+    if next.removed.is_empty() && next.at == previous.at + previous.inserted.len() && !next.inserted.is_empty() {
+        previous.inserted.push_str(&next.inserted);
+        return true;
+    }
+    if !previous.inserted.is_empty() || !next.inserted.is_empty() || next.removed.is_empty() {
+        return false;
+    }
+    // Backspace
+    if next.at + next.removed.len() == previous.at {
+        previous.at = next.at;
+        previous.removed.insert_str(0, &next.removed);
+        return true;
+    }
+    // Delete
+    if next.at == previous.at {
+        previous.removed.push_str(&next.removed);
+        return true;
+    }
+    false
+}
+
+// The single changed span between two texts, found by trimming the common prefix and suffix
+fn diff(old: &str, new: &str) -> Option<Edit> {
+    if old == new {
+        return None;
+    }
+    let mut prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+    Some(Edit {
+        at: prefix,
+        removed: old[prefix..old.len() - suffix].to_owned(),
+        inserted: new[prefix..new.len() - suffix].to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records each state in turn as typing, the way the editor reports keystrokes
+    fn typed(start: &str, states: &[&str]) -> (UndoHistory, String) {
+        let mut history = UndoHistory::new(start.to_owned());
+        for state in states {
+            history.record(state, EditKind::Typing);
+        }
+        (history, states.last().copied().unwrap_or(start).to_owned())
+    }
+
+    #[test]
+    fn backspaces_group() {
+        let (mut history, mut text) = typed("hello", &["hell", "hel", "he"]);
+        assert_eq!(history.undo(&mut text), Some(5));
+        assert_eq!(text, "hello");
+        assert!(!history.can_undo());
+        assert!(history.is_at_saved());
+    }
+
+    #[test]
+    fn deletes_group() {
+        let (mut history, mut text) = typed("hello", &["ello", "llo"]);
+        assert_eq!(history.undo(&mut text), Some(2));
+        assert_eq!(text, "hello");
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn typing_then_backspace_are_separate() {
+        let (mut history, mut text) = typed("", &["a", "ab", "a"]);
+        history.undo(&mut text);
+        assert_eq!(text, "ab");
+        history.undo(&mut text);
+        assert_eq!(text, "");
+        assert_eq!(history.redo(&mut text), Some(2));
+        assert_eq!(text, "ab");
+    }
+
+    #[test]
+    fn diff_stays_on_char_boundaries() {
+        // é and è share their first byte, so the common prefix ends inside a character
+        let edit = diff("aé", "aè").unwrap();
+        assert_eq!((edit.at, edit.removed.as_str(), edit.inserted.as_str()), (1, "é", "è"));
+        // ä and Ĥ share their last byte
+        let edit = diff("äx", "Ĥx").unwrap();
+        assert_eq!((edit.at, edit.removed.as_str(), edit.inserted.as_str()), (0, "ä", "Ĥ"));
+        assert!(diff("日本", "日本").is_none());
+    }
+
+    #[test]
+    fn multi_byte_typing_and_backspace() {
+        let (mut history, mut text) = typed("é", &["é日", "é日本", "é日本語"]);
+        history.undo(&mut text);
+        assert_eq!(text, "é");
+
+        let (mut history, mut text) = typed("日本語", &["日本", "日"]);
+        assert_eq!(history.undo(&mut text), Some("日本語".len()));
+        assert_eq!(text, "日本語");
+    }
+
+    #[test]
+    fn jump_between_branches() {
+        let mut history = UndoHistory::new("a".to_owned());
+        let mut text = "ab".to_owned();
+        history.record(&text, EditKind::Command("B"));
+        history.record("abc", EditKind::Command("C"));
+        text.push('c');
+        let first = history.entries().iter().find(|e| e.is_current).unwrap().node;
+        history.mark_saved();
+
+        // Back to "a" and off on another branch
+        history.undo(&mut text);
+        history.undo(&mut text);
+        assert_eq!(text, "a");
+        history.record("ax", EditKind::Command("X"));
+        text = "ax".to_owned();
+        let second = history.entries().iter().find(|e| e.is_current).unwrap().node;
+
+        assert_eq!(history.jump_to(&mut text, first), Some(3));
+        assert_eq!(text, "abc");
+        assert!(history.is_at_saved());
+
+        assert_eq!(history.jump_to(&mut text, second), Some(2));
+        assert_eq!(text, "ax");
+        assert!(!history.is_at_saved());
+
+        history.jump_to(&mut text, 0);
+        assert_eq!(text, "a");
+        assert!(!history.can_undo());
+        // Redo follows the branch visited last
+        history.redo(&mut text);
+        assert_eq!(text, "ax");
+
+        // Edits after the jump are diffed against the jumped-to text
+        history.jump_to(&mut text, first);
+        history.record("abcd", EditKind::Typing);
+        text = "abcd".to_owned();
+        history.undo(&mut text);
+        assert_eq!(text, "abc");
+        assert!(history.is_at_saved());
+    }
+}
@@ -0,0 +1,69 @@
+// src/watcher.rs
+use eframe::egui;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+// Watches the directories of open files rather than the files themselves: editors
+// (rpad included) save by renaming a new file over the old one, which ends a file watch
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Event>,
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new(ctx: egui::Context) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            if let Ok(event) = result {
+                let _ = sender.send(event);
+                ctx.request_repaint();
+            }
+        })?;
+        Ok(Self {
+            watcher,
+            events,
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+        })
+    }
+
+    // Updates the watch list to exactly the given files
+    pub fn sync<'a>(&mut self, paths: impl Iterator<Item = &'a Path>) {
+        self.files = paths.map(Path::to_path_buf).collect();
+        let dirs: HashSet<PathBuf> = self
+            .files
+            .iter()
+            .filter_map(|p| p.parent())
+            .filter(|d| !d.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .collect();
+
+        for dir in self.dirs.difference(&dirs) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&self.dirs) {
+            let _ = self.watcher.watch(dir, RecursiveMode::NonRecursive);
+        }
+        self.dirs = dirs;
+    }
+
+    // Open files that something touched since the last call
+    pub fn changed_files(&mut self) -> HashSet<PathBuf> {
+        let mut changed = HashSet::new();
+        while let Ok(event) = self.events.try_recv() {
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in event.paths {
+                if self.files.contains(&path) {
+                    changed.insert(path);
+                }
+            }
+        }
+        changed
+    }
+}